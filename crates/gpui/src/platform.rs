@@ -248,6 +248,10 @@ pub trait ScreenCaptureSource {
     /// Returns the video resolution of this source.
     fn resolution(&self) -> Result<Size<Pixels>>;
 
+    /// Returns how many of this source's pixels make up each logical point, e.g. 2
+    /// for a Retina display.
+    fn scale_factor(&self) -> Result<f32>;
+
     /// Start capture video from this source, invoking the given callback
     /// with each frame.
     fn stream(
@@ -12,7 +12,7 @@ use cocoa::{
 use core_foundation::base::TCFType;
 use core_graphics::display::{
     CGDirectDisplayID, CGDisplayCopyDisplayMode, CGDisplayModeGetPixelHeight,
-    CGDisplayModeGetPixelWidth, CGDisplayModeRelease,
+    CGDisplayModeGetPixelWidth, CGDisplayModeGetWidth, CGDisplayModeRelease,
 };
 use ctor::ctor;
 use futures::channel::oneshot;
@@ -60,6 +60,21 @@ impl ScreenCaptureSource for MacScreenCaptureSource {
         }
     }
 
+    fn scale_factor(&self) -> Result<f32> {
+        unsafe {
+            let display_id: CGDirectDisplayID = msg_send![self.sc_display, displayID];
+            let display_mode_ref = CGDisplayCopyDisplayMode(display_id);
+            let pixel_width = CGDisplayModeGetPixelWidth(display_mode_ref);
+            let point_width = CGDisplayModeGetWidth(display_mode_ref);
+            CGDisplayModeRelease(display_mode_ref);
+
+            if point_width == 0 {
+                return Err(anyhow!("display has no logical width"));
+            }
+            Ok(pixel_width as f32 / point_width as f32)
+        }
+    }
+
     fn stream(
         &self,
         frame_callback: Box<dyn Fn(ScreenCaptureFrame)>,
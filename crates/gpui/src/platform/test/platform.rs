@@ -50,6 +50,10 @@ impl ScreenCaptureSource for TestScreenCaptureSource {
         Ok(size(px(1.), px(1.)))
     }
 
+    fn scale_factor(&self) -> Result<f32> {
+        Ok(1.)
+    }
+
     fn stream(
         &self,
         _frame_callback: Box<dyn Fn(ScreenCaptureFrame)>,
@@ -0,0 +1,145 @@
+use crate::{ParticipantIdentity, RoomEvent};
+
+/// The participant a speaker-view layout should show, which only changes once a new
+/// speaker has stayed the loudest for a while.
+#[derive(Default)]
+pub(crate) struct DominantSpeaker {
+    current: Option<ParticipantIdentity>,
+    candidate: Option<ParticipantIdentity>,
+    /// Bumped whenever the candidate changes, so that a pending switch can tell it
+    /// was superseded.
+    generation: u64,
+}
+
+impl DominantSpeaker {
+    /// Returns the generation to confirm after the delay, if the loudest speaker
+    /// became a new candidate. Silence cancels any pending switch, since the
+    /// candidate didn't stay the loudest for the whole delay.
+    pub(crate) fn loudest_speaker_changed(
+        &mut self,
+        loudest: Option<ParticipantIdentity>,
+    ) -> Option<u64> {
+        let Some(loudest) = loudest else {
+            self.candidate = None;
+            self.generation += 1;
+            return None;
+        };
+        if self.current.as_ref() == Some(&loudest) {
+            self.candidate = None;
+            self.generation += 1;
+            None
+        } else if self.candidate.as_ref() == Some(&loudest) {
+            None
+        } else {
+            self.candidate = Some(loudest);
+            self.generation += 1;
+            Some(self.generation)
+        }
+    }
+
+    pub(crate) fn confirm(&mut self, generation: u64) -> Option<RoomEvent> {
+        if generation != self.generation {
+            return None;
+        }
+        let speaker = self.candidate.take()?;
+        let previous = self.current.replace(speaker.clone());
+        Some(RoomEvent::DominantSpeakerChanged { speaker, previous })
+    }
+
+    pub(crate) fn participant_disconnected(&mut self, identity: &ParticipantIdentity) {
+        if self.current.as_ref() == Some(identity) {
+            self.current = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identity(name: &str) -> ParticipantIdentity {
+        ParticipantIdentity(name.to_string())
+    }
+
+    fn confirmed_speaker(event: Option<RoomEvent>) -> Option<ParticipantIdentity> {
+        match event? {
+            RoomEvent::DominantSpeakerChanged { speaker, .. } => Some(speaker),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_switches_after_confirmation() {
+        let mut dominant_speaker = DominantSpeaker::default();
+        let generation = dominant_speaker
+            .loudest_speaker_changed(Some(identity("a")))
+            .unwrap();
+        assert_eq!(
+            dominant_speaker.loudest_speaker_changed(Some(identity("a"))),
+            None
+        );
+        assert_eq!(
+            confirmed_speaker(dominant_speaker.confirm(generation)),
+            Some(identity("a"))
+        );
+        assert_eq!(
+            dominant_speaker.loudest_speaker_changed(Some(identity("a"))),
+            None
+        );
+    }
+
+    #[test]
+    fn test_superseded_candidate_is_not_confirmed() {
+        let mut dominant_speaker = DominantSpeaker::default();
+        let first = dominant_speaker
+            .loudest_speaker_changed(Some(identity("a")))
+            .unwrap();
+        let second = dominant_speaker
+            .loudest_speaker_changed(Some(identity("b")))
+            .unwrap();
+        assert!(dominant_speaker.confirm(first).is_none());
+        assert_eq!(
+            confirmed_speaker(dominant_speaker.confirm(second)),
+            Some(identity("b"))
+        );
+    }
+
+    #[test]
+    fn test_silence_cancels_pending_switch() {
+        let mut dominant_speaker = DominantSpeaker::default();
+        let generation = dominant_speaker
+            .loudest_speaker_changed(Some(identity("a")))
+            .unwrap();
+        assert_eq!(dominant_speaker.loudest_speaker_changed(None), None);
+        assert!(dominant_speaker.confirm(generation).is_none());
+
+        // Speaking up again starts a new delay rather than reviving the old one.
+        let generation = dominant_speaker
+            .loudest_speaker_changed(Some(identity("a")))
+            .unwrap();
+        assert_eq!(
+            confirmed_speaker(dominant_speaker.confirm(generation)),
+            Some(identity("a"))
+        );
+    }
+
+    #[test]
+    fn test_current_speaker_disconnecting() {
+        let mut dominant_speaker = DominantSpeaker::default();
+        let generation = dominant_speaker
+            .loudest_speaker_changed(Some(identity("a")))
+            .unwrap();
+        dominant_speaker.confirm(generation);
+        dominant_speaker.participant_disconnected(&identity("a"));
+        let generation = dominant_speaker
+            .loudest_speaker_changed(Some(identity("b")))
+            .unwrap();
+        let Some(RoomEvent::DominantSpeakerChanged { speaker, previous }) =
+            dominant_speaker.confirm(generation)
+        else {
+            panic!("expected the dominant speaker to change");
+        };
+        assert_eq!(speaker, identity("b"));
+        assert_eq!(previous, None);
+    }
+}
@@ -0,0 +1,135 @@
+use std::sync::Arc;
+
+use collections::{HashMap, HashSet};
+use gpui::App;
+
+use crate::{RemoteTrack, Room, TrackPriority, TrackSid, VideoQuality};
+
+/// A remote video track's tile as currently laid out by the app.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GridTile {
+    pub track_sid: TrackSid,
+    /// The tile's size on screen, in device pixels.
+    pub size: (u32, u32),
+}
+
+/// The tiles an app is currently showing for remote video.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GridLayout {
+    pub tiles: Vec<GridTile>,
+    /// The tile the user is focused on, e.g. the one shown as the main stage.
+    pub focused: Option<TrackSid>,
+}
+
+/// Tiles at least this tall receive the highest quality layer.
+const HIGH_QUALITY_MIN_HEIGHT: u32 = 540;
+/// Tiles at least this tall receive the middle quality layer.
+const MEDIUM_QUALITY_MIN_HEIGHT: u32 = 270;
+
+/// Drives the per-track controls of a room's remote video from the app's grid
+/// layout. Video that isn't on screen is paused, visible tracks are requested at
+/// the quality layer that matches their tile size, and the focused track is given
+/// the highest quality and priority. With [`crate::ConnectOptions::max_decoded_tracks`]
+/// set, those priorities also decide which tracks keep decoding. Qualities are
+/// only requested, never pinned, so the room can still lower them under load.
+pub struct GridManager {
+    room: Arc<Room>,
+    layout: GridLayout,
+}
+
+impl GridManager {
+    pub fn new(room: Arc<Room>) -> Self {
+        Self {
+            room,
+            layout: GridLayout::default(),
+        }
+    }
+
+    pub fn layout(&self) -> &GridLayout {
+        &self.layout
+    }
+
+    /// Reports the app's new layout and applies it to every remote video track.
+    pub fn set_layout(&mut self, layout: GridLayout, cx: &App) {
+        if layout != self.layout {
+            self.layout = layout;
+            self.refresh(cx);
+        }
+    }
+
+    /// Reapplies the current layout. Tracks subscribed afterwards are kept paused
+    /// by the room until they're part of a layout, at which point this requests
+    /// their quality.
+    pub fn refresh(&self, cx: &App) {
+        let tiles = self
+            .layout
+            .tiles
+            .iter()
+            .map(|tile| (&tile.track_sid, tile.size))
+            .collect::<HashMap<_, _>>();
+        // The room gives the focused track its priority and quality, and restores
+        // the previously focused one, so it's left alone below.
+        self.room
+            .set_focused_track(self.layout.focused.as_ref(), cx);
+        for participant in self.room.remote_participants().into_values() {
+            for (sid, publication) in participant.track_publications() {
+                let Some(RemoteTrack::Video(track)) = publication.track() else {
+                    continue;
+                };
+                if self.layout.focused.as_ref() == Some(&sid) {
+                    continue;
+                }
+                match tiles.get(&sid) {
+                    Some(&(_, height)) => {
+                        publication.set_preferred_quality(quality_for_height(height), cx);
+                        track.set_priority(TrackPriority::Standard);
+                    }
+                    None => track.set_priority(TrackPriority::Low),
+                }
+            }
+        }
+        let visible = tiles
+            .into_keys()
+            .chain(self.layout.focused.as_ref())
+            .cloned()
+            .collect::<HashSet<_>>();
+        self.room.set_visible_video_tracks(Some(visible), cx);
+    }
+}
+
+fn quality_for_height(height: u32) -> VideoQuality {
+    if height >= HIGH_QUALITY_MIN_HEIGHT {
+        VideoQuality::High
+    } else if height >= MEDIUM_QUALITY_MIN_HEIGHT {
+        VideoQuality::Medium
+    } else {
+        VideoQuality::Low
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quality_for_height() {
+        assert_eq!(quality_for_height(0), VideoQuality::Low);
+        assert_eq!(
+            quality_for_height(MEDIUM_QUALITY_MIN_HEIGHT - 1),
+            VideoQuality::Low
+        );
+        assert_eq!(
+            quality_for_height(MEDIUM_QUALITY_MIN_HEIGHT),
+            VideoQuality::Medium
+        );
+        assert_eq!(
+            quality_for_height(HIGH_QUALITY_MIN_HEIGHT - 1),
+            VideoQuality::Medium
+        );
+        assert_eq!(
+            quality_for_height(HIGH_QUALITY_MIN_HEIGHT),
+            VideoQuality::High
+        );
+        assert_eq!(quality_for_height(2160), VideoQuality::High);
+    }
+}
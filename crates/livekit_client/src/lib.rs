@@ -1,7 +1,51 @@
 use collections::HashMap;
+use std::time::Duration;
 
+mod grid_manager;
+mod participant_attributes;
+mod participant_waiters;
 mod remote_video_track_view;
+mod room_registry;
+mod subscribers;
+mod track_subscription_rules;
+
+// Only the real client uses these, but they're kept out of it so that their logic
+// can be tested against the mock build.
+#[cfg(any(
+    test,
+    not(any(
+        feature = "test-support",
+        all(target_os = "windows", target_env = "gnu")
+    ))
+))]
+mod dominant_speaker;
+#[cfg(any(
+    test,
+    not(any(
+        feature = "test-support",
+        all(target_os = "windows", target_env = "gnu")
+    ))
+))]
+mod loopback_probe;
+#[cfg(any(
+    test,
+    not(any(
+        feature = "test-support",
+        all(target_os = "windows", target_env = "gnu")
+    ))
+))]
+mod speaking_times;
+#[cfg(any(
+    test,
+    not(any(
+        feature = "test-support",
+        all(target_os = "windows", target_env = "gnu")
+    ))
+))]
+mod track_totals;
+pub use grid_manager::{GridLayout, GridManager, GridTile};
 pub use remote_video_track_view::{RemoteVideoTrackView, RemoteVideoTrackViewEvent};
+pub use room_registry::{RoomId, RoomRegistry};
 
 #[cfg(not(any(
     test,
@@ -35,6 +79,504 @@ pub mod test;
 ))]
 pub use mock_client::*;
 
+#[derive(Clone, Debug)]
+pub struct ConnectOptions {
+    /// How often the room refreshes its cached track statistics. All stats
+    /// accessors read from this cache rather than querying WebRTC themselves.
+    pub stats_interval: Duration,
+    /// Request the lowest quality layer for remote video tracks that are dropping
+    /// frames because they can't be decoded fast enough.
+    pub downgrade_on_decode_overload: bool,
+    /// Retry subscriptions that fail, e.g. due to a transient codec negotiation
+    /// or decryption error, with exponential backoff. Each retry is reported as
+    /// [`RoomEvent::TrackResubscribing`], and giving up as
+    /// [`RoomEvent::TrackResubscribeFailed`].
+    pub resubscribe_on_error: bool,
+    /// The most remote video tracks to decode at once. Beyond this, the least
+    /// relevant tracks (by priority, then by active speaker) are paused until a
+    /// slot frees up.
+    pub max_decoded_tracks: Option<usize>,
+    /// How long someone has to stay the loudest speaker before
+    /// [`RoomEvent::DominantSpeakerChanged`] switches to them, so that brief
+    /// interjections don't flip a speaker-view layout back and forth.
+    pub dominant_speaker_delay: Duration,
+    /// Give up on connecting after this long, e.g. when the server is unreachable.
+    /// By default, connecting waits for as long as the SDK keeps retrying.
+    pub timeout: Option<Duration>,
+    /// Subscribe to every remote track as it's published. When this is off, nothing
+    /// is received until `RemoteTrackPublication::set_subscribed` is called, e.g.
+    /// for the participants on the current page of a large grid.
+    pub auto_subscribe: bool,
+}
+
+impl Default for ConnectOptions {
+    fn default() -> Self {
+        Self {
+            stats_interval: Duration::from_secs(1),
+            downgrade_on_decode_overload: false,
+            resubscribe_on_error: false,
+            max_decoded_tracks: None,
+            dominant_speaker_delay: Duration::from_millis(1500),
+            timeout: None,
+            auto_subscribe: true,
+        }
+    }
+}
+
+/// How long joining a room took, measured from the start of `Room::connect`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ConnectTiming {
+    /// Until the room was connected. The SDK doesn't report signaling, ICE and
+    /// DTLS separately, so this covers all three.
+    pub connected: Duration,
+    /// Until the first remote track was subscribed, if one has been yet.
+    pub first_media: Option<Duration>,
+}
+
+/// A change in a room's connection, as sent to `Room::connection_state_updates`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConnectionStateUpdate {
+    pub state: ConnectionState,
+    /// Why the room was disconnected, e.g. `DUPLICATE_IDENTITY`, `PARTICIPANT_REMOVED`
+    /// or `ROOM_DELETED`. Only set once the room is disconnected.
+    pub reason: Option<&'static str>,
+}
+
+/// WebRTC's native library failed to initialize, so calls can't be joined.
+/// Returned by `ensure_native_loaded` and when connecting to a room.
+#[derive(Clone, Debug)]
+pub struct NativeFrameworkUnavailable {
+    pub reason: String,
+}
+
+impl std::fmt::Display for NativeFrameworkUnavailable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "WebRTC native framework unavailable: {}", self.reason)
+    }
+}
+
+impl std::error::Error for NativeFrameworkUnavailable {}
+
+/// Why connecting or another request to the server failed, so that callers can
+/// retry transient failures and report permanent ones, e.g. a bad token, without
+/// matching on error messages. Errors from `Room` and its participants can be
+/// downcast to this, e.g. with `error.downcast_ref::<LiveKitError>()`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LiveKitError {
+    /// The server couldn't be reached, or the connection dropped while joining.
+    ConnectionFailed(String),
+    /// Only the test server reports this for now. The SDK doesn't expose why the
+    /// server turned a connection away, so the real client reports these rejections
+    /// as `ConnectionFailed`, along with the `TokenExpired` and `RoomFull` ones.
+    TokenInvalid,
+    TokenExpired,
+    RoomFull,
+    /// Joining took longer than [`ConnectOptions::timeout`].
+    Timeout,
+    /// A data message was larger than LiveKit allows for its kind of delivery. See
+    /// [`max_data_payload_size`].
+    PayloadTooLarge {
+        max_size: usize,
+    },
+    /// Any other failure reported by the SDK or WebRTC.
+    Native(String),
+}
+
+impl LiveKitError {
+    /// Whether trying again later could succeed without any change on our side.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            LiveKitError::ConnectionFailed(_) | LiveKitError::Timeout
+        )
+    }
+}
+
+impl std::fmt::Display for LiveKitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LiveKitError::ConnectionFailed(message) => write!(f, "connection failed: {message}"),
+            LiveKitError::TokenInvalid => write!(f, "invalid access token"),
+            LiveKitError::TokenExpired => write!(f, "access token expired"),
+            LiveKitError::RoomFull => write!(f, "room is full"),
+            LiveKitError::Timeout => write!(f, "timed out"),
+            LiveKitError::PayloadTooLarge { max_size } => {
+                write!(f, "payload is larger than the maximum of {max_size} bytes")
+            }
+            LiveKitError::Native(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for LiveKitError {}
+
+#[derive(Clone, Debug)]
+pub struct AudioCaptureOptions {
+    /// Publish the track already muted, so that it is never audible to other
+    /// participants before the caller gets a chance to mute it.
+    pub start_muted: bool,
+    /// Send each audio packet redundantly in the following one (RED), so that
+    /// isolated losses don't cause dropouts. This is on by default; Opus in-band FEC
+    /// is always negotiated in addition.
+    pub red: bool,
+    /// The name of the microphone to capture from. The system default is used when
+    /// this is `None` or no input device has this name.
+    pub input_device: Option<String>,
+}
+
+impl Default for AudioCaptureOptions {
+    fn default() -> Self {
+        Self {
+            start_muted: false,
+            red: true,
+            input_device: None,
+        }
+    }
+}
+
+/// How important a remote track is relative to others when bandwidth or decoding
+/// capacity is scarce.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TrackPriority {
+    Low,
+    #[default]
+    Standard,
+    High,
+}
+
+/// A simulcast layer to receive from a remote video track.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum VideoQuality {
+    Low,
+    Medium,
+    High,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum VideoCodec {
+    #[default]
+    VP8,
+    VP9,
+    H264,
+    AV1,
+}
+
+impl VideoCodec {
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            VideoCodec::VP8 => "video/VP8",
+            VideoCodec::VP9 => "video/VP9",
+            VideoCodec::H264 => "video/H264",
+            VideoCodec::AV1 => "video/AV1",
+        }
+    }
+}
+
+/// Named limits on the resolution a screen share is sent at, for offering in a UI
+/// instead of raw dimensions.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ResolutionPreset {
+    #[default]
+    Original,
+    H1080,
+    H720,
+    H480,
+}
+
+impl ResolutionPreset {
+    /// The box that the captured screen is scaled down to fit within, preserving
+    /// its aspect ratio. Screens already smaller than this are sent as they are.
+    pub fn max_resolution(&self) -> Option<(u32, u32)> {
+        match self {
+            ResolutionPreset::Original => None,
+            ResolutionPreset::H1080 => Some((1920, 1080)),
+            ResolutionPreset::H720 => Some((1280, 720)),
+            ResolutionPreset::H480 => Some((854, 480)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct VideoPublishOptions {
+    pub codec: VideoCodec,
+    /// A second encoding the server forwards to subscribers that cannot decode
+    /// `codec`, e.g. VP8 alongside AV1 for older clients.
+    pub backup_codec: Option<VideoCodec>,
+    pub resolution_preset: ResolutionPreset,
+    /// A custom width and height to scale down to fit within, which takes
+    /// precedence over `resolution_preset`.
+    pub max_resolution: Option<(u32, u32)>,
+}
+
+impl VideoPublishOptions {
+    pub fn effective_max_resolution(&self) -> Option<(u32, u32)> {
+        self.max_resolution
+            .or_else(|| self.resolution_preset.max_resolution())
+    }
+}
+
+/// Options for publishing a screen share.
+#[derive(Clone, Copy, Debug)]
+pub struct ScreenShareOptions {
+    pub video: VideoPublishOptions,
+    /// Send HiDPI displays at their logical resolution rather than every backing
+    /// pixel, e.g. 2560x1440 instead of 5120x2880 for a 5K Retina display. This is
+    /// on by default, since the extra pixels cost far more bandwidth and encoding
+    /// time than they add for most viewers. Turn it off for pixel-perfect sharing.
+    pub downscale_retina: bool,
+}
+
+impl Default for ScreenShareOptions {
+    fn default() -> Self {
+        Self {
+            video: VideoPublishOptions::default(),
+            downscale_retina: true,
+        }
+    }
+}
+
+impl ScreenShareOptions {
+    /// The box that a screen captured at `resolution`, with `scale_factor` pixels
+    /// per logical point, is scaled down to fit within, if any.
+    pub fn effective_max_resolution(
+        &self,
+        resolution: (u32, u32),
+        scale_factor: f32,
+    ) -> Option<(u32, u32)> {
+        let logical_resolution = Some((
+            (resolution.0 as f32 / scale_factor) as u32,
+            (resolution.1 as f32 / scale_factor) as u32,
+        ))
+        .filter(|_| self.downscale_retina && scale_factor > 1.);
+        match (logical_resolution, self.video.effective_max_resolution()) {
+            (Some(logical), Some(max)) => Some((logical.0.min(max.0), logical.1.min(max.1))),
+            (logical, max) => logical.or(max),
+        }
+    }
+}
+
+/// Scales `size` down to fit within `max`, preserving its aspect ratio, as is done
+/// for [`VideoPublishOptions::max_resolution`]. Dimensions are kept even, as I420
+/// buffers require. Sizes that already fit are returned unchanged.
+pub fn fit_within(size: (u32, u32), max: (u32, u32)) -> (u32, u32) {
+    let scale = (max.0 as f64 / size.0 as f64)
+        .min(max.1 as f64 / size.1 as f64)
+        .min(1.);
+    if scale >= 1. {
+        return size;
+    }
+    let even = |dimension: u32| ((dimension as f64 * scale) as u32 & !1).max(2);
+    (even(size.0), even(size.1))
+}
+
+/// The largest payload that can be sent with `Room::publish_data`. Reliable messages
+/// are split across packets and reassembled, up to LiveKit's limit of 15KiB. Lossy
+/// ones are never split, since losing any part would lose the whole message, so they
+/// have to fit in a single packet within the network's MTU.
+pub fn max_data_payload_size(reliable: bool) -> usize {
+    if reliable { 15 * 1024 } else { 1300 }
+}
+
+/// Rejects payloads that `Room::publish_data` can't send, before any packet is built.
+pub(crate) fn check_data_payload_size(payload: &[u8], reliable: bool) -> Result<(), LiveKitError> {
+    let max_size = max_data_payload_size(reliable);
+    if payload.len() > max_size {
+        Err(LiveKitError::PayloadTooLarge { max_size })
+    } else {
+        Ok(())
+    }
+}
+
+/// The number of simulcast layers that will be sent when publishing video at the
+/// given resolution. This mirrors how LiveKit picks encodings: small sources don't
+/// have room for lower-resolution layers, so fewer are produced than requested.
+pub fn max_simulcast_layers_for(width: u32, height: u32) -> u8 {
+    let size = width.max(height);
+    if size >= 960 {
+        3
+    } else if size >= 480 {
+        2
+    } else {
+        1
+    }
+}
+
+/// A segment of speech transcribed by a LiveKit transcription agent.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Transcription {
+    pub participant_sid: String,
+    pub text: String,
+    pub is_final: bool,
+    pub segment_id: String,
+}
+
+/// A payload another participant sent through `Room::publish_data`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DataMessage {
+    /// `None` for messages sent by the server rather than a participant.
+    pub sender_identity: Option<ParticipantIdentity>,
+    pub payload: Vec<u8>,
+}
+
+/// A participant whose hand is up, as reported by `Room::raised_hands`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RaisedHand {
+    pub identity: ParticipantIdentity,
+    pub raised_at: std::time::SystemTime,
+}
+
+/// A participant's answer to whether the room may be recorded. Anyone who hasn't
+/// answered yet, e.g. because their client doesn't ask, is pending, so recorders
+/// should only start once everyone has granted consent.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RecordingConsent {
+    #[default]
+    Pending,
+    Granted,
+    Denied,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TrackStats {
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+}
+
+/// The negotiated parameters of the encoding being received on a track, for
+/// comparing against server logs when a codec won't play.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RtpParameters {
+    pub mime_type: String,
+    pub clock_rate: u32,
+    pub payload_type: u32,
+}
+
+/// A video encoding being received on a track. LiveKit forwards one simulcast layer
+/// to each subscriber, so a track normally has a single one, at the resolution of
+/// whichever layer the server is currently sending.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LayerStats {
+    pub width: u32,
+    pub height: u32,
+    pub frames_per_second: f64,
+    /// Averaged over the last stats interval.
+    pub bitrate_bps: u64,
+}
+
+/// LiveKit's coarse rating of a participant's connection, derived by the server
+/// from their packet loss, jitter and round trip time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConnectionQuality {
+    Excellent,
+    Good,
+    Poor,
+    Lost,
+}
+
+/// The state of a subscription to a remote video track, for a call quality HUD.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SubscriptionStats {
+    /// Averaged over the last stats interval.
+    pub bitrate_bps: u64,
+    /// Since the track was subscribed.
+    pub packets_lost: u64,
+    pub jitter: Duration,
+    pub frames_per_second: f64,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Totals across all of the room's data channels, in both directions.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DataChannelStats {
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub messages_sent: u64,
+    pub messages_received: u64,
+}
+
+/// A local video track's encoder was reconfigured, which briefly lowers quality for
+/// viewers. Either the resolution being encoded or the encoder in use changed, e.g.
+/// after a fallback from hardware to software encoding.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EncoderRestart {
+    pub previous_resolution: (u32, u32),
+    pub resolution: (u32, u32),
+    pub previous_encoder: String,
+    pub encoder: String,
+}
+
+/// The result of connecting to a server with `test_connection`.
+#[derive(Clone, Debug)]
+pub struct ConnectionTest {
+    /// How long it took to join, including signaling, ICE and DTLS.
+    pub connect_time: Duration,
+    /// The round-trip time measured over the media connection, if one was reported
+    /// before disconnecting.
+    pub round_trip_time: Option<Duration>,
+    /// How media was connected, without candidate addresses. A relayed pair means
+    /// direct connections to the server are blocked.
+    pub candidate_pair: Option<CandidatePair>,
+}
+
+/// The ICE candidate pair a room's media is flowing over.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CandidatePair {
+    pub local: IceCandidate,
+    pub remote: IceCandidate,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IceCandidate {
+    /// `host`, `srflx`, `prflx` or `relay`. A relayed pair means media is going
+    /// through a TURN server.
+    pub candidate_type: String,
+    /// `udp` or `tcp`.
+    pub protocol: String,
+    /// Only included when explicitly requested, as it may reveal the user's IP.
+    pub address: Option<String>,
+    pub port: u16,
+}
+
+/// WebRTC's current estimate of the bandwidth available to the room, in bits per
+/// second. Either direction is `None` until that transport has been established.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BandwidthEstimate {
+    pub outgoing: Option<u64>,
+    pub incoming: Option<u64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TrackSource {
+    Unknown,
+    Camera,
+    Microphone,
+    Screenshare,
+    ScreenshareAudio,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ParticipantPermissions {
+    pub can_publish: bool,
+    pub can_subscribe: bool,
+    pub can_publish_data: bool,
+    /// The sources this participant may publish. Empty means any source is allowed
+    /// as long as `can_publish` is set.
+    pub can_publish_sources: Vec<TrackSource>,
+}
+
+impl ParticipantPermissions {
+    /// Whether these permissions allow publishing a camera or a screen share.
+    pub fn can_publish_video(&self) -> bool {
+        self.can_publish
+            && (self.can_publish_sources.is_empty()
+                || self
+                    .can_publish_sources
+                    .iter()
+                    .any(|source| matches!(source, TrackSource::Camera | TrackSource::Screenshare)))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum Participant {
     Local(LocalParticipant),
@@ -84,6 +626,20 @@ pub enum LocalTrack {
     Video(LocalVideoTrack),
 }
 
+/// A remote participant starting or stopping a screen share, which apps typically
+/// react to by switching into or out of a presentation layout.
+#[derive(Clone, Debug)]
+pub enum ScreenShareEvent {
+    Started {
+        participant_sid: ParticipantSid,
+        track: RemoteVideoTrack,
+    },
+    Stopped {
+        participant_sid: ParticipantSid,
+        track_sid: TrackSid,
+    },
+}
+
 #[derive(Clone, Debug)]
 #[non_exhaustive]
 pub enum RoomEvent {
@@ -98,6 +654,12 @@ pub enum RoomEvent {
         publication: LocalTrackPublication,
         participant: LocalParticipant,
     },
+    /// The server unpublished one of our tracks, e.g. because a moderator removed
+    /// it, rather than the app asking to. Sent after the corresponding
+    /// `LocalTrackUnpublished`. The server doesn't report why.
+    LocalTrackUnpublishedByServer {
+        sid: TrackSid,
+    },
     LocalTrackSubscribed {
         track: LocalTrack,
     },
@@ -153,6 +715,13 @@ pub enum RoomEvent {
     ActiveSpeakersChanged {
         speakers: Vec<Participant>,
     },
+    /// The loudest speaker changed and has held that position for
+    /// [`ConnectOptions::dominant_speaker_delay`]. The dominant speaker stays the
+    /// same while nobody is speaking.
+    DominantSpeakerChanged {
+        speaker: ParticipantIdentity,
+        previous: Option<ParticipantIdentity>,
+    },
     ConnectionStateChanged(ConnectionState),
     Connected {
         participants_with_tracks: Vec<(RemoteParticipant, Vec<RemoteTrackPublication>)>,
@@ -162,4 +731,91 @@ pub enum RoomEvent {
     },
     Reconnecting,
     Reconnected,
+    /// Remote video is arriving faster than it can be decoded and frames are being
+    /// dropped. Emitted once each time the room becomes overloaded.
+    DecodeOverloaded {
+        track_sids: Vec<TrackSid>,
+    },
+    /// Remote audio is now playing through `device`, either because it's the first
+    /// output device used or because the previous one changed or disappeared.
+    AudioOutputChanged {
+        device: Option<String>,
+    },
+    /// A subscription that previously failed is being retried. `attempt` starts at 1.
+    TrackResubscribing {
+        participant: RemoteParticipant,
+        track_sid: TrackSid,
+        attempt: u32,
+    },
+    /// Retrying a failed subscription didn't help, and no more attempts will be
+    /// made. Any later failure for the track starts the retries over.
+    TrackResubscribeFailed {
+        participant: RemoteParticipant,
+        track_sid: TrackSid,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_within() {
+        // Sizes that already fit are left alone, even when they're odd.
+        assert_eq!(fit_within((1279, 719), (1280, 720)), (1279, 719));
+        assert_eq!(fit_within((2560, 1440), (1280, 720)), (1280, 720));
+        // The aspect ratio is kept, scaling by whichever dimension is tighter.
+        assert_eq!(fit_within((3840, 1080), (1920, 1080)), (1920, 540));
+        assert_eq!(fit_within((1080, 1920), (1280, 720)), (404, 720));
+        // Scaled dimensions are rounded down to even, but never to zero.
+        assert_eq!(fit_within((1000, 502), (500, 500)), (500, 250));
+        assert_eq!(fit_within((4096, 8), (128, 128)), (128, 2));
+    }
+
+    #[test]
+    fn test_screen_share_effective_max_resolution() {
+        let retina = ((5120, 2880), 2.);
+        let options = ScreenShareOptions::default();
+        assert_eq!(
+            options.effective_max_resolution(retina.0, retina.1),
+            Some((2560, 1440))
+        );
+        // Displays without HiDPI scaling are left alone.
+        assert_eq!(options.effective_max_resolution((1920, 1080), 1.), None);
+        // Whichever of the logical resolution and the requested maximum is smaller
+        // wins.
+        let options = ScreenShareOptions {
+            video: VideoPublishOptions {
+                resolution_preset: ResolutionPreset::H1080,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            options.effective_max_resolution(retina.0, retina.1),
+            Some((1920, 1080))
+        );
+        let options = ScreenShareOptions {
+            downscale_retina: false,
+            ..Default::default()
+        };
+        assert_eq!(options.effective_max_resolution(retina.0, retina.1), None);
+    }
+
+    #[test]
+    fn test_check_data_payload_size() {
+        assert_eq!(check_data_payload_size(&[0; 15 * 1024], true), Ok(()));
+        assert_eq!(
+            check_data_payload_size(&[0; 15 * 1024 + 1], true),
+            Err(LiveKitError::PayloadTooLarge {
+                max_size: 15 * 1024
+            })
+        );
+        // Lossy messages have to fit in a single packet.
+        assert_eq!(check_data_payload_size(&[0; 1300], false), Ok(()));
+        assert_eq!(
+            check_data_payload_size(&[0; 1301], false),
+            Err(LiveKitError::PayloadTooLarge { max_size: 1300 })
+        );
+    }
 }
@@ -1,90 +1,924 @@
-use std::sync::Arc;
+use std::path::Path;
+use std::sync::{
+    Arc, OnceLock, Weak,
+    atomic::{AtomicBool, Ordering},
+};
+use std::time::{Duration, Instant, SystemTime};
 
 use anyhow::Result;
-use collections::HashMap;
-use futures::{SinkExt, channel::mpsc};
-use gpui::{App, AsyncApp, ScreenCaptureSource, ScreenCaptureStream, Task};
+use collections::{HashMap, HashSet};
+use futures::{FutureExt as _, StreamExt as _, channel::mpsc, future::BoxFuture};
+use gpui::{App, AsyncApp, BackgroundExecutor, ScreenCaptureSource, ScreenCaptureStream, Task};
 use gpui_tokio::Tokio;
+use livekit::webrtc::stats::RtcStats;
+use parking_lot::Mutex;
 use playback::capture_local_video_track;
 
 mod playback;
+mod recording;
+mod stats;
 
-use crate::{LocalTrack, Participant, RemoteTrack, RoomEvent, TrackPublication};
+use crate::dominant_speaker::DominantSpeaker;
+use crate::participant_attributes::{self, HAND_RAISED_AT, RECORDING_CONSENT};
+use crate::participant_waiters::{self, ParticipantWaiters};
+use crate::speaking_times::{SpeakingTimes, speaking_time};
+use crate::subscribers::Subscribers;
+use crate::track_subscription_rules::TrackSubscriptionRules;
+use crate::{
+    AudioCaptureOptions, BandwidthEstimate, CandidatePair, ConnectOptions, ConnectTiming,
+    ConnectionQuality, ConnectionStateUpdate, ConnectionTest, DataChannelStats, DataMessage,
+    EncoderRestart, LayerStats, LiveKitError, LocalTrack, NativeFrameworkUnavailable, Participant,
+    ParticipantPermissions, RaisedHand, RecordingConsent, RemoteTrack, RoomEvent, RtpParameters,
+    ScreenShareEvent, ScreenShareOptions, SubscriptionStats, TrackPriority, TrackPublication,
+    TrackSource, TrackStats, Transcription, VideoCodec, VideoQuality,
+};
 pub use playback::AudioStream;
-pub(crate) use playback::{RemoteVideoFrame, play_remote_video_track};
+pub use playback::{RemoteVideoFrame, TimedVideoFrame};
+pub(crate) use playback::{
+    play_local_video_track, play_remote_video_track, play_timed_remote_video_track,
+};
+pub use recording::RecordingHandle;
 
+// Handles keep a reference to the room they came from, whose state, such as the
+// latest stats, they read from.
 #[derive(Clone, Debug)]
-pub struct RemoteVideoTrack(livekit::track::RemoteVideoTrack);
+pub struct RemoteVideoTrack(livekit::track::RemoteVideoTrack, Weak<RoomState>);
 #[derive(Clone, Debug)]
-pub struct RemoteAudioTrack(livekit::track::RemoteAudioTrack);
+pub struct RemoteAudioTrack(livekit::track::RemoteAudioTrack, Weak<RoomState>);
 #[derive(Clone, Debug)]
-pub struct RemoteTrackPublication(livekit::publication::RemoteTrackPublication);
+pub struct RemoteTrackPublication(
+    livekit::publication::RemoteTrackPublication,
+    Weak<RoomState>,
+);
 #[derive(Clone, Debug)]
-pub struct RemoteParticipant(livekit::participant::RemoteParticipant);
+pub struct RemoteParticipant(livekit::participant::RemoteParticipant, Weak<RoomState>);
 
 #[derive(Clone, Debug)]
-pub struct LocalVideoTrack(livekit::track::LocalVideoTrack);
+pub struct LocalVideoTrack(
+    livekit::track::LocalVideoTrack,
+    /// Whether the local preview of this track is flipped horizontally.
+    Arc<AtomicBool>,
+    Weak<RoomState>,
+);
 #[derive(Clone, Debug)]
-pub struct LocalAudioTrack(livekit::track::LocalAudioTrack);
+pub struct LocalAudioTrack(livekit::track::LocalAudioTrack, Weak<RoomState>);
 #[derive(Clone, Debug)]
 pub struct LocalTrackPublication(livekit::publication::LocalTrackPublication);
 #[derive(Clone, Debug)]
-pub struct LocalParticipant(livekit::participant::LocalParticipant);
+pub struct LocalParticipant(livekit::participant::LocalParticipant, Weak<RoomState>);
 
 pub struct Room {
-    room: livekit::Room,
+    room: Arc<livekit::Room>,
+    state: Arc<RoomState>,
+    executor: BackgroundExecutor,
     _task: Task<()>,
     playback: playback::AudioStack,
+    _stats_task: Task<()>,
+}
+
+/// The parts of a [`Room`] that its event handler and stats poller keep up to date,
+/// which are dropped along with the room.
+#[derive(Default)]
+struct RoomState {
+    subscribed_sources: Mutex<Option<HashSet<TrackSource>>>,
+    participant_waiters: ParticipantWaiters,
+    transcriptions: Subscribers<Transcription>,
+    active_speaker_updates: Subscribers<Vec<ParticipantIdentity>>,
+    screen_share_updates: Subscribers<ScreenShareEvent>,
+    data_messages: Subscribers<DataMessage>,
+    hands_updates: Subscribers<Vec<RaisedHand>>,
+    recording_consent_updates: Subscribers<(ParticipantIdentity, RecordingConsent)>,
+    connection_state_updates: Subscribers<ConnectionStateUpdate>,
+    connection_quality_updates: Subscribers<(ParticipantIdentity, ConnectionQuality)>,
+    paused_tracks: Mutex<HashSet<TrackSid>>,
+    max_decoded_tracks: Option<usize>,
+    visible_video_tracks: Mutex<Option<HashSet<TrackSid>>>,
+    /// The active speakers as of the last update, loudest first, which decide what
+    /// to keep decoding when the focused track changes.
+    active_speakers: Mutex<Vec<livekit::id::ParticipantIdentity>>,
+    /// The focused track, along with the priority it had before being focused.
+    focused_track: Mutex<Option<(TrackSid, TrackPriority)>>,
+    track_subscription_rules: Mutex<TrackSubscriptionRules>,
+    connect_timing: Mutex<ConnectTiming>,
+    /// Tracks that [`LocalParticipant::unpublish_track`] is unpublishing, so that any
+    /// other unpublish can be attributed to the server.
+    unpublish_requests: Mutex<HashSet<TrackSid>>,
+    remote_track_settings: Mutex<RemoteTrackSettings>,
+    audio_levels: Arc<playback::AudioLevelListeners>,
+    stats: stats::StatsCache,
 }
 
 pub type TrackSid = livekit::id::TrackSid;
+pub type ParticipantSid = livekit::id::ParticipantSid;
 pub type ConnectionState = livekit::ConnectionState;
 #[derive(Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
 pub struct ParticipantIdentity(pub String);
 
+/// What the app has asked for about the remote tracks it's subscribed to, keyed by
+/// sid rather than stored on the track, since a new handle is created for every
+/// event. Entries are forgotten once their track is unsubscribed or the room
+/// disconnects.
+#[derive(Default)]
+struct RemoteTrackSettings {
+    /// Priorities set through [`RemoteVideoTrack::set_priority`].
+    priorities: HashMap<TrackSid, TrackPriority>,
+    /// Qualities set through [`RemoteTrackPublication::pin_quality`], which the
+    /// room's own adaptive decisions must leave alone.
+    pinned_qualities: HashMap<TrackSid, VideoQuality>,
+}
+
+impl RemoteTrackSettings {
+    fn priority(&self, sid: &TrackSid) -> TrackPriority {
+        self.priorities.get(sid).copied().unwrap_or_default()
+    }
+
+    fn set_priority(&mut self, sid: &TrackSid, priority: TrackPriority) {
+        if priority == TrackPriority::default() {
+            self.priorities.remove(sid);
+        } else {
+            self.priorities.insert(sid.clone(), priority);
+        }
+    }
+
+    fn pinned_quality(&self, sid: &TrackSid) -> Option<VideoQuality> {
+        self.pinned_qualities.get(sid).copied()
+    }
+
+    fn forget(&mut self, sid: &TrackSid) {
+        self.priorities.remove(sid);
+        self.pinned_qualities.remove(sid);
+    }
+
+    fn clear(&mut self) {
+        self.priorities.clear();
+        self.pinned_qualities.clear();
+    }
+}
+
+/// Wraps an SDK error so that callers can downcast it to a [`LiveKitError`]. The
+/// pinned SDK folds the server's responses into its error messages rather than
+/// exposing their status codes, and those messages aren't stable enough to match
+/// on, so every SDK error becomes `fallback`.
+fn room_error(
+    action: &str,
+    error: livekit::RoomError,
+    fallback: fn(String) -> LiveKitError,
+) -> anyhow::Error {
+    let message = error.to_string();
+    anyhow::Error::new(fallback(message.clone())).context(format!("failed to {action}: {message}"))
+}
+
+fn raised_hands(room: &livekit::Room) -> Vec<RaisedHand> {
+    let local_participant = room.local_participant();
+    let local = (
+        ParticipantIdentity(local_participant.identity().0),
+        local_participant.attributes().remove(HAND_RAISED_AT),
+    );
+    let remote = room.remote_participants().into_values().map(|participant| {
+        (
+            ParticipantIdentity(participant.identity().0),
+            participant.attributes().remove(HAND_RAISED_AT),
+        )
+    });
+    participant_attributes::raised_hands(std::iter::once(local).chain(remote))
+}
+
+/// Keeps only the `max_decoded_tracks` most relevant visible remote video tracks
+/// enabled, ranked by priority and then by active speaker order, and pauses the
+/// rest. Returns the publications whose enabled state needs to change.
+fn clamp_decoded_tracks(
+    room: &livekit::Room,
+    state: &RoomState,
+) -> Vec<(livekit::publication::RemoteTrackPublication, bool)> {
+    let active_speakers = state.active_speakers.lock().clone();
+    let mut tracks = room
+        .remote_participants()
+        .into_iter()
+        .flat_map(|(identity, participant)| {
+            let speaker_rank = active_speakers
+                .iter()
+                .position(|speaker| *speaker == identity)
+                .unwrap_or(usize::MAX);
+            participant
+                .track_publications()
+                .into_values()
+                .filter(|publication| {
+                    publication.kind() == livekit::track::TrackKind::Video
+                        && publication.is_subscribed()
+                })
+                .map(move |publication| (publication, speaker_rank))
+        })
+        .collect::<Vec<_>>();
+    let settings = state.remote_track_settings.lock();
+    tracks.sort_by_key(|(publication, speaker_rank)| {
+        (
+            std::cmp::Reverse(settings.priority(&publication.sid())),
+            *speaker_rank,
+            publication.sid().to_string(),
+        )
+    });
+
+    let visible_tracks = state.visible_video_tracks.lock();
+    let mut paused_tracks = state.paused_tracks.lock();
+    let mut still_paused = HashSet::default();
+    let mut changes = Vec::new();
+    let mut decoded = 0;
+    for (publication, _) in tracks {
+        let sid = publication.sid();
+        let visible = visible_tracks
+            .as_ref()
+            .is_none_or(|visible| visible.contains(&sid));
+        let pause = !visible || state.max_decoded_tracks.is_some_and(|max| decoded >= max);
+        if !pause {
+            decoded += 1;
+        }
+        if pause != paused_tracks.contains(&sid) {
+            changes.push((publication, !pause));
+        }
+        if pause {
+            still_paused.insert(sid);
+        }
+    }
+    *paused_tracks = still_paused;
+    changes
+}
+
+/// Checks that WebRTC's native library can be initialized, so that apps can disable
+/// calling features up front rather than crashing on first use. The check only runs
+/// once; later calls return the cached result.
+///
+/// This only catches initialization failures that WebRTC reports by panicking.
+/// The library is linked into the binary rather than loaded at runtime, so there's
+/// nothing to probe with `dlopen`: a missing system dependency stops the process
+/// from starting at all, and a crash inside native code (e.g. a failed C++ check)
+/// aborts it rather than unwinding, so neither can be reported here.
+pub fn ensure_native_loaded() -> Result<(), NativeFrameworkUnavailable> {
+    static LOADED: OnceLock<Result<(), NativeFrameworkUnavailable>> = OnceLock::new();
+    LOADED
+        .get_or_init(|| {
+            std::panic::catch_unwind(|| {
+                libwebrtc::native::apm::AudioProcessingModule::new(false, false, false, false);
+            })
+            .map_err(|panic| {
+                let reason = panic
+                    .downcast_ref::<&str>()
+                    .map(|reason| reason.to_string())
+                    .or_else(|| panic.downcast_ref::<String>().cloned())
+                    .unwrap_or_else(|| "unknown error".into());
+                log::error!("failed to initialize WebRTC: {reason}");
+                NativeFrameworkUnavailable { reason }
+            })
+        })
+        .clone()
+}
+
+fn room_options() -> livekit::RoomOptions {
+    let connector = tokio_tungstenite::Connector::Rustls(Arc::new(http_client_tls::tls_config()));
+    let mut config = livekit::RoomOptions::default();
+    config.connector = Some(connector);
+    config
+}
+
+/// Joins a room and immediately leaves it again, to check that a server is reachable
+/// and the token is accepted, e.g. for a "test connection" button in settings.
+pub async fn test_connection(
+    url: String,
+    token: String,
+    timeout: Duration,
+    cx: &mut AsyncApp,
+) -> Result<ConnectionTest> {
+    ensure_native_loaded()?;
+    let started_at = Instant::now();
+    let config = room_options();
+    // Spawned directly on the runtime so that it's left running rather than aborted
+    // when we stop waiting for it, and still closes the room once it connects.
+    let tokio = cx.update(|cx| Tokio::handle(cx))?;
+    let connect = tokio.spawn(async move {
+        let (room, _events) = livekit::Room::connect(&url, &token, config).await?;
+        let connect_time = started_at.elapsed();
+        let stats = room.get_stats().await;
+        room.close().await.ok();
+        anyhow::Ok((connect_time, stats?))
+    });
+    let timer = cx.background_executor().timer(timeout);
+    let (connect_time, stats) = match futures::future::select(connect, timer).await {
+        futures::future::Either::Left((result, _)) => result??,
+        futures::future::Either::Right(_) => {
+            anyhow::bail!("server did not respond within {:?}", timeout)
+        }
+    };
+    let mut candidate_pair = stats::selected_candidate_pair(&stats.subscriber_stats);
+    if let Some(candidate_pair) = &mut candidate_pair {
+        candidate_pair.local.address = None;
+        candidate_pair.remote.address = None;
+    }
+    Ok(ConnectionTest {
+        connect_time,
+        round_trip_time: stats::round_trip_time(&stats.subscriber_stats),
+        candidate_pair,
+    })
+}
+
+/// Captures a single frame from a screen capture source, scaled down to fit within
+/// `max_size`, so a picker can preview sources before one is shared. Fails if the
+/// source has gone away since it was enumerated, or doesn't produce a frame within
+/// a couple of seconds.
+pub async fn screen_capture_thumbnail(
+    source: &dyn ScreenCaptureSource,
+    max_size: (u32, u32),
+    cx: &AsyncApp,
+) -> Result<RemoteVideoFrame> {
+    playback::capture_thumbnail(source, max_size, cx.background_executor()).await
+}
+
+/// LiveKit's subscription permissions cover all of a participant's tracks at once,
+/// so restricting a single track means listing every remote participant along with
+/// the tracks they may still subscribe to. This also has to be redone whenever a
+/// participant joins, since anyone not listed is denied everything.
+fn track_subscription_permissions(
+    room: &livekit::Room,
+    rules: &TrackSubscriptionRules,
+) -> (bool, Vec<livekit::participant::ParticipantTrackPermission>) {
+    let published = room
+        .local_participant()
+        .track_publications()
+        .into_keys()
+        .collect::<Vec<_>>();
+    if !published.iter().any(|sid| rules.is_restricted(sid)) {
+        return (true, Vec::new());
+    }
+    let permissions = room
+        .remote_participants()
+        .into_keys()
+        .map(|identity| {
+            let participant = ParticipantIdentity(identity.0.clone());
+            let allowed_track_sids = published
+                .iter()
+                .filter(|sid| rules.is_allowed(sid, &participant))
+                .cloned()
+                .collect();
+            livekit::participant::ParticipantTrackPermission {
+                participant_identity: identity,
+                allow_all: false,
+                allowed_track_sids,
+            }
+        })
+        .collect();
+    (false, permissions)
+}
+
+const RESUBSCRIBE_MAX_ATTEMPTS: u32 = 5;
+const RESUBSCRIBE_BASE_DELAY: Duration = Duration::from_millis(500);
+
+/// Spawns a future on the Tokio runtime, which the SDK's calls have to run on. This
+/// is type-erased since the runtime's handle can only be named through Tokio itself.
+type SpawnOnTokio = Arc<dyn Fn(BoxFuture<'static, ()>) + Send + Sync>;
+
+/// Keeps a room's [`RoomState`] up to date with the SDK's events, and forwards them
+/// to the app as [`RoomEvent`]s.
+struct RoomEventHandler {
+    room: Weak<livekit::Room>,
+    state: Arc<RoomState>,
+    tx: mpsc::UnboundedSender<RoomEvent>,
+    executor: BackgroundExecutor,
+    tokio: SpawnOnTokio,
+    connect_started_at: Instant,
+    resubscribe_on_error: bool,
+    dominant_speaker_delay: Duration,
+    resubscribe_attempts: HashMap<TrackSid, u32>,
+    dominant_speaker: Arc<Mutex<DominantSpeaker>>,
+    speaking_times: SpeakingTimes,
+}
+
+impl RoomEventHandler {
+    fn handle(&mut self, event: livekit::RoomEvent) {
+        let mut unpublished_by_server = None;
+        match &event {
+            livekit::RoomEvent::ParticipantConnected(participant) => {
+                self.state
+                    .participant_waiters
+                    .participant_connected(&RemoteParticipant(
+                        participant.clone(),
+                        Arc::downgrade(&self.state),
+                    ));
+            }
+            livekit::RoomEvent::ParticipantDisconnected(participant) => {
+                self.participant_disconnected(participant);
+            }
+            livekit::RoomEvent::TrackPublished { publication, .. } => {
+                self.track_published(publication);
+            }
+            livekit::RoomEvent::TrackUnpublished { publication, .. } => {
+                self.resubscribe_attempts.remove(&publication.sid());
+            }
+            livekit::RoomEvent::TrackSubscribed { publication, .. } => {
+                self.track_subscribed(publication);
+            }
+            livekit::RoomEvent::TrackUnsubscribed {
+                track, publication, ..
+            } => {
+                self.track_unsubscribed(track, publication);
+            }
+            // Disconnections are reported along with their reason below.
+            livekit::RoomEvent::ConnectionStateChanged(state)
+                if *state != ConnectionState::Disconnected =>
+            {
+                self.state
+                    .connection_state_updates
+                    .broadcast(ConnectionStateUpdate {
+                        state: *state,
+                        reason: None,
+                    });
+            }
+            livekit::RoomEvent::Disconnected { reason } => {
+                self.state.remote_track_settings.lock().clear();
+                self.resubscribe_attempts.clear();
+                self.state
+                    .connection_state_updates
+                    .broadcast(ConnectionStateUpdate {
+                        state: ConnectionState::Disconnected,
+                        reason: Some(reason.as_str_name()),
+                    });
+            }
+            livekit::RoomEvent::ConnectionQualityChanged {
+                quality,
+                participant,
+            } => {
+                self.state.connection_quality_updates.broadcast((
+                    ParticipantIdentity(participant.identity().0),
+                    ConnectionQuality::from(*quality),
+                ));
+            }
+            livekit::RoomEvent::TrackSubscriptionFailed {
+                participant,
+                track_sid,
+                ..
+            } if self.resubscribe_on_error => {
+                self.track_subscription_failed(participant, track_sid);
+            }
+            livekit::RoomEvent::TranscriptionReceived {
+                participant,
+                segments,
+                ..
+            } => {
+                let participant_sid = participant
+                    .as_ref()
+                    .map(|participant| participant.sid().to_string())
+                    .unwrap_or_default();
+                for segment in segments {
+                    self.state.transcriptions.broadcast(Transcription {
+                        participant_sid: participant_sid.clone(),
+                        text: segment.text.clone(),
+                        is_final: segment.r#final,
+                        segment_id: segment.id.clone(),
+                    });
+                }
+            }
+            livekit::RoomEvent::ParticipantAttributesChanged {
+                participant,
+                changed_attributes,
+            } => {
+                if let Some(consent) = changed_attributes.get(RECORDING_CONSENT) {
+                    self.state.recording_consent_updates.broadcast((
+                        ParticipantIdentity(participant.identity().0),
+                        participant_attributes::recording_consent(Some(consent.as_str())),
+                    ));
+                }
+                if changed_attributes.contains_key(HAND_RAISED_AT) {
+                    self.hands_changed();
+                }
+            }
+            livekit::RoomEvent::DataReceived {
+                payload,
+                participant,
+                ..
+            } => {
+                self.state.data_messages.broadcast(DataMessage {
+                    sender_identity: participant
+                        .as_ref()
+                        .map(|participant| ParticipantIdentity(participant.identity().0)),
+                    payload: payload.to_vec(),
+                });
+            }
+            livekit::RoomEvent::ActiveSpeakersChanged { speakers } => {
+                self.active_speakers_changed(speakers);
+            }
+            livekit::RoomEvent::LocalTrackUnpublished { publication, .. } => {
+                unpublished_by_server = self.local_track_unpublished(publication);
+            }
+            _ => {}
+        }
+        if let Some(screen_share) = screen_share_event(&event, &Arc::downgrade(&self.state)) {
+            self.state.screen_share_updates.broadcast(screen_share);
+        }
+        let affects_permissions = matches!(
+            event,
+            livekit::RoomEvent::ParticipantConnected(_)
+                | livekit::RoomEvent::LocalTrackPublished { .. }
+                | livekit::RoomEvent::LocalTrackUnpublished { .. }
+        );
+        if affects_permissions {
+            self.update_track_subscription_permissions();
+        }
+        let affects_decoding = matches!(
+            event,
+            livekit::RoomEvent::TrackSubscribed { .. }
+                | livekit::RoomEvent::TrackUnsubscribed { .. }
+                | livekit::RoomEvent::ActiveSpeakersChanged { .. }
+                | livekit::RoomEvent::ParticipantDisconnected(_)
+        );
+        if affects_decoding {
+            self.clamp_decoded_tracks();
+        }
+        if let Some(event) = room_event_from_livekit(event, &Arc::downgrade(&self.state)) {
+            self.tx.unbounded_send(event).ok();
+        }
+        if let Some(sid) = unpublished_by_server {
+            self.tx
+                .unbounded_send(RoomEvent::LocalTrackUnpublishedByServer { sid })
+                .ok();
+        }
+    }
+
+    fn spawn_on_tokio(&self, future: impl Future<Output = ()> + Send + 'static) {
+        (self.tokio)(future.boxed());
+    }
+
+    fn participant_disconnected(&self, participant: &livekit::participant::RemoteParticipant) {
+        self.dominant_speaker
+            .lock()
+            .participant_disconnected(&ParticipantIdentity(participant.identity().0));
+        if participant.attributes().contains_key(HAND_RAISED_AT) {
+            self.hands_changed();
+        }
+    }
+
+    fn track_published(&self, publication: &livekit::publication::RemoteTrackPublication) {
+        if let Some(sources) = self.state.subscribed_sources.lock().as_ref() {
+            let publication = publication.clone();
+            let subscribed = sources.contains(&publication.source().into());
+            self.spawn_on_tokio(async move { publication.set_subscribed(subscribed) });
+        }
+    }
+
+    fn track_subscribed(&mut self, publication: &livekit::publication::RemoteTrackPublication) {
+        self.resubscribe_attempts.remove(&publication.sid());
+        self.state
+            .connect_timing
+            .lock()
+            .first_media
+            .get_or_insert_with(|| self.connect_started_at.elapsed());
+    }
+
+    fn track_unsubscribed(
+        &mut self,
+        track: &livekit::track::RemoteTrack,
+        publication: &livekit::publication::RemoteTrackPublication,
+    ) {
+        let sid = publication.sid();
+        self.state.remote_track_settings.lock().forget(&sid);
+        if let livekit::track::RemoteTrack::Audio(track) = track {
+            self.state.audio_levels.remove(&track.rtc_track().id());
+        }
+        let mut focused_track = self.state.focused_track.lock();
+        if focused_track
+            .as_ref()
+            .is_some_and(|(focused, _)| *focused == sid)
+        {
+            *focused_track = None;
+        }
+    }
+
+    fn track_subscription_failed(
+        &mut self,
+        participant: &livekit::participant::RemoteParticipant,
+        track_sid: &TrackSid,
+    ) {
+        let attempt = self
+            .resubscribe_attempts
+            .entry(track_sid.clone())
+            .or_default();
+        *attempt += 1;
+        if *attempt > RESUBSCRIBE_MAX_ATTEMPTS {
+            self.resubscribe_attempts.remove(track_sid);
+            self.tx
+                .unbounded_send(RoomEvent::TrackResubscribeFailed {
+                    participant: RemoteParticipant(
+                        participant.clone(),
+                        Arc::downgrade(&self.state),
+                    ),
+                    track_sid: track_sid.clone(),
+                })
+                .ok();
+            return;
+        }
+        self.tx
+            .unbounded_send(RoomEvent::TrackResubscribing {
+                participant: RemoteParticipant(participant.clone(), Arc::downgrade(&self.state)),
+                track_sid: track_sid.clone(),
+                attempt: *attempt,
+            })
+            .ok();
+        let timer = self
+            .executor
+            .timer(RESUBSCRIBE_BASE_DELAY * 2u32.pow(*attempt - 1));
+        let participant = participant.clone();
+        let track_sid = track_sid.clone();
+        let tokio = self.tokio.clone();
+        self.executor
+            .spawn(async move {
+                timer.await;
+                let Some(publication) = participant.track_publications().get(&track_sid).cloned()
+                else {
+                    return;
+                };
+                // Toggling the subscription off first makes the SDK send a fresh request
+                // rather than treating it as a no-op.
+                tokio(
+                    async move {
+                        publication.set_subscribed(false);
+                        publication.set_subscribed(true);
+                    }
+                    .boxed(),
+                );
+            })
+            .detach();
+    }
+
+    fn active_speakers_changed(&mut self, speakers: &[livekit::participant::Participant]) {
+        let active_speakers = speakers
+            .iter()
+            .map(|speaker| speaker.identity())
+            .collect::<Vec<_>>();
+        self.speaking_times.active_speakers_changed(
+            speakers.iter().map(|speaker| speaker.sid().to_string()),
+            Instant::now(),
+        );
+        self.state.active_speaker_updates.broadcast(
+            active_speakers
+                .iter()
+                .map(|identity| ParticipantIdentity(identity.0.clone()))
+                .collect(),
+        );
+        let loudest = active_speakers
+            .first()
+            .map(|identity| ParticipantIdentity(identity.0.clone()));
+        *self.state.active_speakers.lock() = active_speakers;
+
+        let generation = self
+            .dominant_speaker
+            .lock()
+            .loudest_speaker_changed(loudest);
+        if let Some(generation) = generation {
+            let timer = self.executor.timer(self.dominant_speaker_delay);
+            let dominant_speaker = self.dominant_speaker.clone();
+            let tx = self.tx.clone();
+            self.executor
+                .spawn(async move {
+                    timer.await;
+                    if let Some(event) = dominant_speaker.lock().confirm(generation) {
+                        tx.unbounded_send(event).ok();
+                    }
+                })
+                .detach();
+        }
+    }
+
+    fn hands_changed(&self) {
+        if let Some(room) = self.room.upgrade() {
+            self.state.hands_updates.broadcast(raised_hands(&room));
+        }
+    }
+
+    /// Returns the track's sid if the server unpublished it rather than us.
+    fn local_track_unpublished(
+        &self,
+        publication: &livekit::publication::LocalTrackPublication,
+    ) -> Option<TrackSid> {
+        self.state
+            .track_subscription_rules
+            .lock()
+            .remove_track(&publication.sid());
+        if let Some(livekit::track::LocalTrack::Audio(track)) = publication.track() {
+            self.state.audio_levels.remove(&track.rtc_track().id());
+        }
+        let requested = self
+            .state
+            .unpublish_requests
+            .lock()
+            .remove(&publication.sid());
+        // Tracks are also unpublished while leaving the room, which isn't the server's
+        // doing either.
+        let connected = self
+            .room
+            .upgrade()
+            .is_some_and(|room| room.connection_state() == ConnectionState::Connected);
+        (!requested && connected).then(|| publication.sid())
+    }
+
+    fn update_track_subscription_permissions(&self) {
+        let Some(room) = self.room.upgrade() else {
+            return;
+        };
+        let (all_allowed, permissions) =
+            track_subscription_permissions(&room, &self.state.track_subscription_rules.lock());
+        self.spawn_on_tokio(async move {
+            if let Err(error) = room
+                .local_participant()
+                .set_track_subscription_permissions(all_allowed, permissions)
+                .await
+            {
+                log::error!("failed to set subscription permissions: {error}");
+            }
+        });
+    }
+
+    fn clamp_decoded_tracks(&self) {
+        if self.state.max_decoded_tracks.is_none()
+            && self.state.visible_video_tracks.lock().is_none()
+        {
+            return;
+        }
+        let Some(room) = self.room.upgrade() else {
+            return;
+        };
+        let changes = clamp_decoded_tracks(&room, &self.state);
+        if !changes.is_empty() {
+            self.spawn_on_tokio(async move {
+                for (publication, enabled) in changes {
+                    publication.set_enabled(enabled);
+                }
+            });
+        }
+    }
+}
+
+/// The screen share starting or stopping, if that's what `event` reports.
+fn screen_share_event(
+    event: &livekit::RoomEvent,
+    room: &Weak<RoomState>,
+) -> Option<ScreenShareEvent> {
+    match event {
+        livekit::RoomEvent::TrackSubscribed {
+            track: livekit::track::RemoteTrack::Video(track),
+            publication,
+            participant,
+        } if publication.source() == livekit::track::TrackSource::Screenshare => {
+            Some(ScreenShareEvent::Started {
+                participant_sid: participant.sid(),
+                track: RemoteVideoTrack(track.clone(), room.clone()),
+            })
+        }
+        livekit::RoomEvent::TrackUnsubscribed {
+            publication,
+            participant,
+            ..
+        } if publication.source() == livekit::track::TrackSource::Screenshare => {
+            Some(ScreenShareEvent::Stopped {
+                participant_sid: participant.sid(),
+                track_sid: publication.sid(),
+            })
+        }
+        _ => None,
+    }
+}
+
 impl Room {
     pub async fn connect(
         url: String,
         token: String,
         cx: &mut AsyncApp,
     ) -> Result<(Self, mpsc::UnboundedReceiver<RoomEvent>)> {
-        let connector =
-            tokio_tungstenite::Connector::Rustls(Arc::new(http_client_tls::tls_config()));
-        let mut config = livekit::RoomOptions::default();
-        config.connector = Some(connector);
-        let (room, mut events) = Tokio::spawn(cx, async move {
-            livekit::Room::connect(&url, &token, config).await
-        })?
-        .await??;
+        Self::connect_with_options(url, token, ConnectOptions::default(), cx).await
+    }
 
-        let (mut tx, rx) = mpsc::unbounded();
+    pub async fn connect_with_options(
+        url: String,
+        token: String,
+        options: ConnectOptions,
+        cx: &mut AsyncApp,
+    ) -> Result<(Self, mpsc::UnboundedReceiver<RoomEvent>)> {
+        ensure_native_loaded()?;
+        let connect_started_at = Instant::now();
+        let mut config = room_options();
+        config.auto_subscribe = options.auto_subscribe;
+        let stats_interval = options.stats_interval;
+        let downgrade_on_decode_overload = options.downgrade_on_decode_overload;
+        let resubscribe_on_error = options.resubscribe_on_error;
+        let max_decoded_tracks = options.max_decoded_tracks;
+        let dominant_speaker_delay = options.dominant_speaker_delay;
+        let timeout = options.timeout;
+        let timed_out = Arc::new(AtomicBool::new(false));
+        // Spawned directly on the runtime rather than through `Tokio::spawn`, which
+        // would abort the task on timeout before it could leave the room.
+        let tokio = cx.update(|cx| Tokio::handle(cx))?;
+        let connect = tokio.spawn({
+            let url = url.clone();
+            let timed_out = timed_out.clone();
+            async move {
+                let (room, events) =
+                    livekit::Room::connect(&url, &token, config)
+                        .await
+                        .map_err(|error| {
+                            room_error("connect", error, LiveKitError::ConnectionFailed)
+                        })?;
+                // Nobody is waiting for this room anymore, so leave it rather than
+                // keeping a session open that will never be used.
+                if timed_out.load(Ordering::SeqCst) {
+                    room.close().await.ok();
+                    anyhow::bail!("connected after timing out");
+                }
+                anyhow::Ok((room, events))
+            }
+        });
+        let (room, mut events) = match timeout {
+            Some(timeout) => {
+                let timer = cx.background_executor().timer(timeout);
+                match futures::future::select(connect, timer).await {
+                    futures::future::Either::Left((result, _)) => result??,
+                    futures::future::Either::Right(_) => {
+                        timed_out.store(true, Ordering::SeqCst);
+                        return Err(anyhow::Error::new(LiveKitError::Timeout)
+                            .context(format!("timed out connecting to {url} after {timeout:?}")));
+                    }
+                }
+            }
+            None => connect.await??,
+        };
+
+        let room = Arc::new(room);
+        let state = Arc::new(RoomState {
+            max_decoded_tracks,
+            connect_timing: Mutex::new(ConnectTiming {
+                connected: connect_started_at.elapsed(),
+                first_media: None,
+            }),
+            ..Default::default()
+        });
+        let (tx, rx) = mpsc::unbounded();
+        let stats_tx = tx.clone();
+        let audio_tx = tx.clone();
+        let mut handler = RoomEventHandler {
+            room: Arc::downgrade(&room),
+            state: state.clone(),
+            tx,
+            executor: cx.background_executor().clone(),
+            tokio: Arc::new(move |future: BoxFuture<'static, ()>| {
+                tokio.spawn(future);
+            }),
+            connect_started_at,
+            resubscribe_on_error,
+            dominant_speaker_delay,
+            resubscribe_attempts: HashMap::default(),
+            dominant_speaker: Arc::default(),
+            speaking_times: SpeakingTimes::default(),
+        };
         let task = cx.background_executor().spawn(async move {
             while let Some(event) = events.recv().await {
-                if let Some(event) = room_event_from_livekit(event) {
-                    tx.send(event).await.ok();
-                }
+                handler.handle(event);
             }
         });
 
+        let stats_task = stats::poll_stats(
+            Arc::downgrade(&room),
+            state.clone(),
+            stats_interval,
+            downgrade_on_decode_overload,
+            stats_tx,
+            cx,
+        );
+
         Ok((
             Self {
                 room,
+                state,
+                executor: cx.background_executor().clone(),
                 _task: task,
-                playback: playback::AudioStack::new(cx.background_executor().clone()),
+                playback: playback::AudioStack::new(
+                    cx.background_executor().clone(),
+                    audio_tx,
+                    state.audio_levels.clone(),
+                ),
+                _stats_task: stats_task,
             },
             rx,
         ))
     }
 
     pub fn local_participant(&self) -> LocalParticipant {
-        LocalParticipant(self.room.local_participant())
+        LocalParticipant(self.room.local_participant(), Arc::downgrade(&self.state))
     }
 
     pub fn remote_participants(&self) -> HashMap<ParticipantIdentity, RemoteParticipant> {
         self.room
             .remote_participants()
             .into_iter()
-            .map(|(k, v)| (ParticipantIdentity(k.0), RemoteParticipant(v)))
+            .map(|(k, v)| {
+                (
+                    ParticipantIdentity(k.0),
+                    RemoteParticipant(v, Arc::downgrade(&self.state)),
+                )
+            })
             .collect()
     }
 
@@ -92,17 +926,388 @@ impl Room {
         self.room.connection_state()
     }
 
+    /// Leaves the room, resolving once the connection has been torn down, so that
+    /// another room can be joined straight away. Dropping the room also leaves it,
+    /// but in the background.
+    pub async fn disconnect(&self, cx: &mut AsyncApp) -> Result<()> {
+        let room = self.room.clone();
+        Tokio::spawn(cx, async move { room.close().await })?
+            .await?
+            .map_err(|error| room_error("disconnect", error, LiveKitError::Native))
+    }
+
+    /// A breakdown of how long joining this room took, for diagnosing slow joins.
+    pub fn connect_timing(&self) -> ConnectTiming {
+        *self.state.connect_timing.lock()
+    }
+
+    /// Sent whenever the room starts reconnecting, reconnects, or is disconnected,
+    /// e.g. after the network drops or the server removes us. Unlike the events
+    /// returned from `connect`, any number of these can be subscribed.
+    pub fn connection_state_updates(&self) -> mpsc::UnboundedReceiver<ConnectionStateUpdate> {
+        self.state.connection_state_updates.subscribe()
+    }
+
+    /// The server's rating of each participant's connection whenever it changes,
+    /// including the local participant's under their own identity, for showing a
+    /// quality indicator next to each name without polling stats.
+    pub fn connection_quality_updates(
+        &self,
+    ) -> mpsc::UnboundedReceiver<(ParticipantIdentity, ConnectionQuality)> {
+        self.state.connection_quality_updates.subscribe()
+    }
+
+    /// Remote video tracks that aren't being decoded because of
+    /// [`ConnectOptions::max_decoded_tracks`] or [`Self::set_visible_video_tracks`],
+    /// which should be shown as placeholders.
+    pub fn paused_video_tracks(&self) -> Vec<TrackSid> {
+        self.state.paused_tracks.lock().iter().cloned().collect()
+    }
+
+    /// Gives one remote video track precedence over all others, e.g. the shared
+    /// screen during a presentation, so that it stays smooth while thumbnails
+    /// degrade. The focused track is requested at its highest quality unless its
+    /// quality is pinned, is the last to be paused by
+    /// [`ConnectOptions::max_decoded_tracks`], and is never downgraded on decode
+    /// overload. Passing `None` returns the track to the priority it had before.
+    pub fn set_focused_track(&self, track_sid: Option<&TrackSid>, cx: &App) {
+        let pinned = {
+            let mut focused_track = self.state.focused_track.lock();
+            if focused_track.as_ref().map(|(sid, _)| sid) == track_sid {
+                return;
+            }
+            let mut settings = self.state.remote_track_settings.lock();
+            if let Some((sid, priority)) = focused_track.take() {
+                settings.set_priority(&sid, priority);
+            }
+            if let Some(sid) = track_sid {
+                *focused_track = Some((sid.clone(), settings.priority(sid)));
+                settings.set_priority(sid, TrackPriority::High);
+            }
+            track_sid.is_some_and(|sid| settings.pinned_quality(sid).is_some())
+        };
+
+        if let Some(sid) = track_sid.filter(|_| !pinned) {
+            let publication = self
+                .room
+                .remote_participants()
+                .into_values()
+                .find_map(|participant| participant.track_publications().remove(sid));
+            if let Some(publication) = publication {
+                Tokio::spawn(cx, async move {
+                    publication.set_video_quality(livekit::track::VideoQuality::High)
+                })
+                .detach();
+            }
+        }
+        self.clamp_decoded_tracks(cx);
+    }
+
+    pub fn focused_track(&self) -> Option<TrackSid> {
+        self.state
+            .focused_track
+            .lock()
+            .as_ref()
+            .map(|(sid, _)| sid.clone())
+    }
+
+    /// Pauses every remote video track that isn't in `track_sids`, e.g. because it's
+    /// scrolled out of view, including ones subscribed later on, and lets the rest
+    /// be decoded as [`ConnectOptions::max_decoded_tracks`] allows. Passing `None`
+    /// makes every track eligible again. Going through the room rather than
+    /// [`RemoteTrackPublication::set_enabled`] keeps the two from undoing each other.
+    pub fn set_visible_video_tracks(&self, track_sids: Option<HashSet<TrackSid>>, cx: &App) {
+        *self.state.visible_video_tracks.lock() = track_sids;
+        self.clamp_decoded_tracks(cx);
+    }
+
+    /// Reapplies [`ConnectOptions::max_decoded_tracks`] straight away, rather than
+    /// waiting for the next change in subscriptions or active speakers.
+    fn clamp_decoded_tracks(&self, cx: &App) {
+        // This also runs without any restrictions, to resume whatever they paused.
+        let changes = clamp_decoded_tracks(&self.room, &self.state);
+        if !changes.is_empty() {
+            Tokio::spawn(cx, async move {
+                for (publication, enabled) in changes {
+                    publication.set_enabled(enabled);
+                }
+            })
+            .detach();
+        }
+    }
+
+    /// Live captions published by transcription agents in the room, one message per
+    /// segment update. Interim segments are followed by a final one with the same id.
+    pub fn transcriptions_received(&self) -> mpsc::UnboundedReceiver<Transcription> {
+        self.state.transcriptions.subscribe()
+    }
+
+    /// The identities of everyone currently speaking, loudest first, sent each time
+    /// someone starts or stops talking. An empty list means nobody is speaking.
+    pub fn active_speaker_updates(&self) -> mpsc::UnboundedReceiver<Vec<ParticipantIdentity>> {
+        self.state.active_speaker_updates.subscribe()
+    }
+
+    /// Remote screen shares starting and stopping, with the track to render as soon
+    /// as one starts. Shares that are already running when we join are reported as
+    /// they're subscribed.
+    pub fn screen_share_updates(&self) -> mpsc::UnboundedReceiver<ScreenShareEvent> {
+        self.state.screen_share_updates.subscribe()
+    }
+
+    /// Sends `payload` to everyone else in the room, or only to the participants in
+    /// `destination`. Reliable messages are retransmitted and arrive in order, e.g.
+    /// for chat, while lossy ones suit frequent updates such as cursor positions,
+    /// where a late message is worse than a dropped one. Payloads larger than
+    /// [`crate::max_data_payload_size`] fail with [`LiveKitError::PayloadTooLarge`];
+    /// lossy messages have to fit in a single packet, so they're much smaller.
+    pub async fn publish_data(
+        &self,
+        payload: &[u8],
+        reliable: bool,
+        destination: Option<&[ParticipantIdentity]>,
+        cx: &mut AsyncApp,
+    ) -> Result<()> {
+        crate::check_data_payload_size(payload, reliable)?;
+        let participant = self.room.local_participant();
+        let packet = livekit::DataPacket {
+            payload: payload.to_vec(),
+            topic: None,
+            reliable,
+            destination_identities: destination
+                .unwrap_or_default()
+                .iter()
+                .map(|identity| livekit::id::ParticipantIdentity(identity.0.clone()))
+                .collect(),
+        };
+        Tokio::spawn(cx, async move { participant.publish_data(packet).await })?
+            .await?
+            .map_err(|error| room_error("publish data", error, LiveKitError::Native))
+    }
+
+    /// Raises or lowers the local participant's hand. Raising a hand that's already
+    /// up keeps its original place in line. This is stored in a participant
+    /// attribute, so other LiveKit clients that read the same attribute see it too.
+    pub async fn raise_hand(&self, raised: bool, cx: &mut AsyncApp) -> Result<()> {
+        let participant = self.room.local_participant();
+        if raised == participant.attributes().contains_key(HAND_RAISED_AT) {
+            return Ok(());
+        }
+        let value = if raised {
+            participant_attributes::hand_raised_at_value(SystemTime::now())
+        } else {
+            // LiveKit removes attributes that are set to an empty string.
+            String::new()
+        };
+        let attributes = [(HAND_RAISED_AT.to_string(), value)].into_iter().collect();
+        Tokio::spawn(
+            cx,
+            async move { participant.set_attributes(attributes).await },
+        )?
+        .await?
+        .map_err(|error| anyhow::anyhow!("failed to update raised hand: {error}"))
+    }
+
+    /// Everyone in the room with their hand up, including the local participant,
+    /// in the order they raised them.
+    pub fn raised_hands(&self) -> Vec<RaisedHand> {
+        raised_hands(&self.room)
+    }
+
+    /// The full list from [`Self::raised_hands`], sent again whenever a hand goes up
+    /// or down or a participant with a raised hand leaves.
+    pub fn hands_updates(&self) -> mpsc::UnboundedReceiver<Vec<RaisedHand>> {
+        self.state.hands_updates.subscribe()
+    }
+
+    /// Records the local participant's answer to whether the room may be recorded,
+    /// in a participant attribute that every client in the room can read.
+    pub async fn set_recording_consent(&self, granted: bool, cx: &mut AsyncApp) -> Result<()> {
+        let participant = self.room.local_participant();
+        let attributes = [(
+            RECORDING_CONSENT.to_string(),
+            participant_attributes::recording_consent_value(granted),
+        )]
+        .into_iter()
+        .collect();
+        Tokio::spawn(
+            cx,
+            async move { participant.set_attributes(attributes).await },
+        )?
+        .await?
+        .map_err(|error| anyhow::anyhow!("failed to update recording consent: {error}"))
+    }
+
+    /// Sent whenever a participant, including the local one, grants or revokes
+    /// consent, so that a recorder can stop as soon as anyone revokes it.
+    pub fn recording_consent_updates(
+        &self,
+    ) -> mpsc::UnboundedReceiver<(ParticipantIdentity, RecordingConsent)> {
+        self.state.recording_consent_updates.subscribe()
+    }
+
+    /// Payloads published by other participants through [`Self::publish_data`].
+    pub fn data_messages(&self) -> mpsc::UnboundedReceiver<DataMessage> {
+        self.state.data_messages.subscribe()
+    }
+
+    /// Resolves with the given participant once they're in the room, or fails if
+    /// they haven't joined within `timeout`.
+    pub fn wait_for_participant(
+        &self,
+        identity: &str,
+        timeout: Duration,
+    ) -> impl Future<Output = Result<RemoteParticipant>> + use<> {
+        let identity = ParticipantIdentity(identity.to_string());
+        let joined = self.state.participant_waiters.register(identity.clone());
+        let present = self.remote_participants().remove(&identity);
+        participant_waiters::wait_for_participant(
+            identity,
+            present,
+            joined,
+            timeout,
+            self.executor.clone(),
+        )
+    }
+
+    /// Only subscribe to remote tracks from the given sources, e.g. to show screen
+    /// shares without paying for everyone's camera. This applies to tracks that are
+    /// already published as well as any that get published later.
+    pub fn subscribe_sources(&self, sources: &[TrackSource], cx: &App) {
+        let sources = sources.iter().copied().collect::<HashSet<_>>();
+        self.update_subscriptions(
+            |publication| sources.contains(&publication.source().into()),
+            cx,
+        );
+        *self.state.subscribed_sources.lock() = Some(sources);
+    }
+
+    /// Undoes [`Room::subscribe_sources`], subscribing to every remote track again.
+    pub fn subscribe_all_sources(&self, cx: &App) {
+        *self.state.subscribed_sources.lock() = None;
+        self.update_subscriptions(|_| true, cx);
+    }
+
+    fn update_subscriptions(
+        &self,
+        is_subscribed: impl Fn(&livekit::publication::RemoteTrackPublication) -> bool,
+        cx: &App,
+    ) {
+        let subscriptions = self
+            .room
+            .remote_participants()
+            .into_values()
+            .flat_map(|participant| participant.track_publications().into_values())
+            .map(|publication| {
+                let subscribed = is_subscribed(&publication);
+                (publication, subscribed)
+            })
+            .collect::<Vec<_>>();
+        Tokio::spawn(cx, async move {
+            for (publication, subscribed) in subscriptions {
+                publication.set_subscribed(subscribed);
+            }
+        })
+        .detach();
+    }
+
+    /// Restricts which participants can subscribe to one of our published tracks,
+    /// e.g. to share a screen with moderators only. Passing `None` allows everyone
+    /// again. This requires a token that allows updating subscription permissions.
+    pub async fn set_track_subscription_allowed(
+        &self,
+        track_sid: &TrackSid,
+        participants: Option<&[ParticipantIdentity]>,
+        cx: &mut AsyncApp,
+    ) -> Result<()> {
+        self.state
+            .track_subscription_rules
+            .lock()
+            .set_allowed(track_sid, participants);
+        self.update_track_subscription_permissions(cx).await
+    }
+
+    /// Prevents specific participants from subscribing to one of our published
+    /// tracks, e.g. recording bots. Passing `None` clears the list. Anyone on both
+    /// this list and the one from [`Self::set_track_subscription_allowed`] is denied.
+    pub async fn set_track_subscription_denied(
+        &self,
+        track_sid: &TrackSid,
+        participants: Option<&[ParticipantIdentity]>,
+        cx: &mut AsyncApp,
+    ) -> Result<()> {
+        self.state
+            .track_subscription_rules
+            .lock()
+            .set_denied(track_sid, participants);
+        self.update_track_subscription_permissions(cx).await
+    }
+
+    async fn update_track_subscription_permissions(&self, cx: &mut AsyncApp) -> Result<()> {
+        let (all_allowed, permissions) =
+            track_subscription_permissions(&self.room, &self.state.track_subscription_rules.lock());
+        let participant = self.room.local_participant();
+        Tokio::spawn(cx, async move {
+            participant
+                .set_track_subscription_permissions(all_allowed, permissions)
+                .await
+        })?
+        .await?
+        .map_err(|error| anyhow::anyhow!("failed to set subscription permissions: {error}"))
+    }
+
+    pub fn track_stats(&self, sid: &TrackSid) -> Option<TrackStats> {
+        self.state.stats.track_stats(sid)
+    }
+
+    pub fn data_stats(&self) -> DataChannelStats {
+        self.state.stats.data_channel_stats()
+    }
+
+    /// The ICE candidate pair media is currently flowing over, as of the last stats
+    /// poll, for diagnosing relayed or failing connections. Candidate addresses are
+    /// omitted unless `include_addresses` is set, since they can reveal the user's IP.
+    pub fn selected_candidate_pair(&self, include_addresses: bool) -> Option<CandidatePair> {
+        let mut pair = self.state.stats.selected_candidate_pair()?;
+        if !include_addresses {
+            pair.local.address = None;
+            pair.remote.address = None;
+        }
+        Some(pair)
+    }
+
+    /// WebRTC's estimates of the available bandwidth, sent whenever they change
+    /// (checked every [`ConnectOptions::stats_interval`]).
+    pub fn available_bandwidth_updates(&self) -> mpsc::UnboundedReceiver<BandwidthEstimate> {
+        self.state.stats.bandwidth_updates()
+    }
+
     pub async fn publish_local_microphone_track(
         &self,
         cx: &mut AsyncApp,
     ) -> Result<(LocalTrackPublication, playback::AudioStream)> {
-        let (track, stream) = self.playback.capture_local_microphone_track()?;
+        self.publish_local_microphone_track_with_options(AudioCaptureOptions::default(), cx)
+            .await
+    }
+
+    pub async fn publish_local_microphone_track_with_options(
+        &self,
+        options: AudioCaptureOptions,
+        cx: &mut AsyncApp,
+    ) -> Result<(LocalTrackPublication, playback::AudioStream)> {
+        let (track, stream) = self
+            .playback
+            .capture_local_microphone_track(options.input_device)?;
+        if options.start_muted {
+            track.mute();
+        }
         let publication = self
             .local_participant()
             .publish_track(
-                livekit::track::LocalTrack::Audio(track.0),
+                livekit::track::LocalTrack::Audio(track),
                 livekit::options::TrackPublishOptions {
                     source: livekit::track::TrackSource::Microphone,
+                    red: options.red,
                     ..Default::default()
                 },
                 cx,
@@ -127,22 +1332,97 @@ impl Room {
     ) -> Result<playback::AudioStream> {
         Ok(self.playback.play_remote_audio_track(&track.0))
     }
+
+    /// Plays a remote audio track through a specific output device, e.g. one
+    /// participant on headphones and the rest on speakers. `None` returns the track to
+    /// the default device. If the device disappears, the track falls back to the
+    /// default device until it returns.
+    pub fn set_audio_output_device(&self, track: &RemoteAudioTrack, device_name: Option<String>) {
+        self.playback
+            .set_output_device(track.0.sid().as_str(), device_name);
+    }
+
+    /// Checks that the microphone can hear the speakers, for use in a device-check
+    /// screen. Resolves with the round-trip latency between playing a probe and
+    /// hearing it, or an error if it isn't heard.
+    pub fn measure_audio_loopback_latency(&self) -> impl Future<Output = Result<Duration>> + use<> {
+        self.playback.measure_loopback_latency()
+    }
+}
+
+/// Exercises local devices without connecting to a room, e.g. for a device check
+/// screen shown before joining a call. Capture stops when this is dropped.
+pub struct DevicePreview {
+    _task: Task<()>,
+}
+
+impl DevicePreview {
+    /// Starts capturing from the named microphone, or the default one, returning
+    /// its audio level (from 0 to 1) for every 10ms of audio.
+    pub fn microphone(
+        device_name: Option<String>,
+        cx: &App,
+    ) -> (Self, mpsc::UnboundedReceiver<f32>) {
+        let (task, levels) = playback::preview_microphone(cx.background_executor(), device_name);
+        (Self { _task: task }, levels)
+    }
 }
 
 impl LocalParticipant {
+    pub fn identity(&self) -> ParticipantIdentity {
+        ParticipantIdentity(self.0.identity().0)
+    }
+
+    /// The sid the server assigned to us when joining, or `None` if it hasn't yet.
+    pub fn sid(&self) -> Option<ParticipantSid> {
+        Some(self.0.sid()).filter(|sid| !sid.as_str().is_empty())
+    }
+
+    pub fn recording_consent(&self) -> RecordingConsent {
+        participant_attributes::recording_consent(
+            self.0
+                .attributes()
+                .get(RECORDING_CONSENT)
+                .map(String::as_str),
+        )
+    }
+
+    /// How long we've been an active speaker since joining the room. This starts
+    /// over on every connection and is forgotten once the room is dropped.
+    pub fn speaking_time(&self) -> Duration {
+        speaking_time(self.0.sid().as_str())
+    }
+
     pub async fn publish_screenshare_track(
         &self,
         source: &dyn ScreenCaptureSource,
         cx: &mut AsyncApp,
     ) -> Result<(LocalTrackPublication, Box<dyn ScreenCaptureStream>)> {
-        let (track, stream) = capture_local_video_track(source, cx).await?;
+        self.publish_screenshare_track_with_options(source, ScreenShareOptions::default(), cx)
+            .await
+    }
+
+    pub async fn publish_screenshare_track_with_options(
+        &self,
+        source: &dyn ScreenCaptureSource,
+        options: ScreenShareOptions,
+        cx: &mut AsyncApp,
+    ) -> Result<(LocalTrackPublication, Box<dyn ScreenCaptureStream>)> {
+        let resolution = source.resolution()?;
+        let max_resolution = options.effective_max_resolution(
+            (resolution.width.0 as u32, resolution.height.0 as u32),
+            source.scale_factor()?,
+        );
+        let (track, stream) = capture_local_video_track(source, max_resolution, cx).await?;
+        let options = options.video;
         let options = livekit::options::TrackPublishOptions {
             source: livekit::track::TrackSource::Screenshare,
-            video_codec: livekit::options::VideoCodec::VP8,
+            video_codec: options.codec.into(),
+            backup_codec: options.backup_codec.map(Into::into),
             ..Default::default()
         };
         let publication = self
-            .publish_track(livekit::track::LocalTrack::Video(track.0), options, cx)
+            .publish_track(livekit::track::LocalTrack::Video(track), options, cx)
             .await?;
 
         Ok((publication, stream))
@@ -160,7 +1440,7 @@ impl LocalParticipant {
         })?
         .await?
         .map(LocalTrackPublication)
-        .map_err(|error| anyhow::anyhow!("failed to publish track: {error}"))
+        .map_err(|error| room_error("publish track", error, LiveKitError::Native))
     }
 
     pub async fn unpublish_track(
@@ -169,10 +1449,20 @@ impl LocalParticipant {
         cx: &mut AsyncApp,
     ) -> Result<LocalTrackPublication> {
         let participant = self.0.clone();
-        Tokio::spawn(cx, async move { participant.unpublish_track(&sid).await })?
-            .await?
+        if let Some(room) = self.1.upgrade() {
+            room.unpublish_requests.lock().insert(sid.clone());
+        }
+        let result = Tokio::spawn(cx, {
+            let sid = sid.clone();
+            async move { participant.unpublish_track(&sid).await }
+        })?
+        .await?;
+        if let Some(room) = self.1.upgrade().filter(|_| result.is_err()) {
+            room.unpublish_requests.lock().remove(&sid);
+        }
+        result
             .map(LocalTrackPublication)
-            .map_err(|error| anyhow::anyhow!("failed to unpublish track: {error}"))
+            .map_err(|error| room_error("unpublish track", error, LiveKitError::Native))
     }
 }
 
@@ -207,25 +1497,296 @@ impl RemoteParticipant {
         ParticipantIdentity(self.0.identity().0)
     }
 
+    /// Unlike the identity, this is assigned by the server on every join, so a
+    /// participant that rejoins gets a new sid.
+    pub fn sid(&self) -> ParticipantSid {
+        self.0.sid()
+    }
+
+    pub fn recording_consent(&self) -> RecordingConsent {
+        participant_attributes::recording_consent(
+            self.0
+                .attributes()
+                .get(RECORDING_CONSENT)
+                .map(String::as_str),
+        )
+    }
+
+    /// How long this participant has been an active speaker since we joined the
+    /// room, including while they're speaking right now. This starts over on every
+    /// connection and is forgotten once the room is dropped.
+    pub fn speaking_time(&self) -> Duration {
+        speaking_time(self.0.sid().as_str())
+    }
+
+    /// Whether this participant could ever publish video, so callers can lay out
+    /// audio-only participants without waiting for a track. SIP (dial-in)
+    /// participants only carry audio, and others need permission to publish a
+    /// camera or screen share.
+    pub fn has_video_capability(&self) -> bool {
+        self.0.kind() != livekit::participant::ParticipantKind::Sip
+            && self.permissions().can_publish_video()
+    }
+
+    pub fn permissions(&self) -> ParticipantPermissions {
+        let Some(permission) = self.0.permission() else {
+            return ParticipantPermissions::default();
+        };
+        ParticipantPermissions {
+            can_publish: permission.can_publish,
+            can_subscribe: permission.can_subscribe,
+            can_publish_data: permission.can_publish_data,
+            can_publish_sources: permission
+                .can_publish_sources
+                .into_iter()
+                .filter_map(|source| livekit::proto::TrackSource::try_from(source).ok())
+                .map(|source| livekit::track::TrackSource::from(source).into())
+                .collect(),
+        }
+    }
+
     pub fn track_publications(&self) -> HashMap<TrackSid, RemoteTrackPublication> {
         self.0
             .track_publications()
             .into_iter()
-            .map(|(sid, publication)| (sid, RemoteTrackPublication(publication)))
+            .map(|(sid, publication)| (sid, RemoteTrackPublication(publication, self.1.clone())))
             .collect()
     }
 }
 
+impl LocalVideoTrack {
+    pub(crate) fn new(track: livekit::track::LocalVideoTrack, room: Weak<RoomState>) -> Self {
+        Self(track, Arc::default(), room)
+    }
+
+    /// Mirrors how this track is rendered locally, as is conventional for a
+    /// front-facing camera's self view. This is purely a render-time transform,
+    /// surfaced as [`TimedVideoFrame::mirrored`] on frames from
+    /// [`Self::add_local_renderer`]: the frames that are encoded and sent to other
+    /// participants are never mirrored.
+    pub fn set_self_view_mirrored(&self, mirrored: bool) {
+        self.1.store(mirrored, Ordering::Relaxed);
+    }
+
+    pub fn is_self_view_mirrored(&self) -> bool {
+        self.1.load(Ordering::Relaxed)
+    }
+
+    /// The resolution currently being sent, which may be lower than the source's when
+    /// the encoder is adapting to limited bandwidth or CPU. With simulcast, this is
+    /// the resolution of the highest layer.
+    pub fn current_resolution(&self) -> Option<(u32, u32)> {
+        self.2
+            .upgrade()?
+            .stats
+            .with_track_stats(&self.0.sid(), |stats| {
+                stats
+                    .iter()
+                    .filter_map(|stat| match stat {
+                        RtcStats::OutboundRtp(outbound) if outbound.outbound.frame_width > 0 => {
+                            Some((
+                                outbound.outbound.frame_width,
+                                outbound.outbound.frame_height,
+                            ))
+                        }
+                        _ => None,
+                    })
+                    .max_by_key(|(width, height)| width * height)
+            })?
+    }
+
+    /// Notifies whenever this track's encoder is reconfigured, for correlating
+    /// glitches reported by viewers. Restarts are detected from the room's periodic
+    /// stats, so they're reported up to one stats interval late.
+    pub fn encoder_restarts(&self) -> mpsc::UnboundedReceiver<EncoderRestart> {
+        match self.2.upgrade() {
+            Some(room) => room.stats.encoder_restarts(&self.0.sid()),
+            None => mpsc::unbounded().1,
+        }
+    }
+
+    /// The bytes sent on this track since it was published, across all simulcast
+    /// layers, as of the last stats poll. This keeps counting across reconnects,
+    /// but resets if the track is republished, since it then has a new sid.
+    pub fn total_bytes_sent(&self) -> u64 {
+        self.2.upgrade().map_or(0, |room| {
+            room.stats.total_track_stats(&self.0.sid()).bytes_sent
+        })
+    }
+
+    /// Whether this track is being encoded in hardware, as of the last stats poll.
+    /// Software encoding is a common cause of high CPU usage during screen sharing.
+    /// `None` until the track has been polled at least once.
+    pub fn is_hardware_accelerated(&self) -> Option<bool> {
+        self.2
+            .upgrade()?
+            .stats
+            .is_hardware_accelerated(&self.0.sid())
+    }
+
+    /// How long frames took on average over the last stats interval to be encoded
+    /// and then sent. A high value while the network is healthy points at the
+    /// capture and encode pipeline. `None` until the track has been polled twice.
+    pub fn capture_to_encode_latency(&self) -> Option<Duration> {
+        self.2
+            .upgrade()?
+            .stats
+            .capture_to_encode_latency(&self.0.sid())
+    }
+
+    /// Delivers this track's frames as they're captured, before encoding, so that
+    /// a presenter can preview exactly what they're sharing without a round trip
+    /// through the server. Frames stop being delivered when the task is dropped.
+    pub fn add_local_renderer(
+        &self,
+        mut callback: impl FnMut(TimedVideoFrame) + Send + 'static,
+        cx: &App,
+    ) -> Task<()> {
+        let frames = play_local_video_track(self);
+        cx.background_executor().spawn(async move {
+            let mut frames = std::pin::pin!(frames);
+            while let Some(frame) = frames.next().await {
+                callback(frame);
+            }
+        })
+    }
+}
+
 impl RemoteAudioTrack {
     pub fn sid(&self) -> TrackSid {
         self.0.sid()
     }
+
+    /// Whether this track carried no meaningful audio over the last stats interval,
+    /// regardless of whether it's muted. Useful for noticing a publisher whose
+    /// microphone isn't working.
+    pub fn is_silent(&self) -> bool {
+        self.1
+            .upgrade()
+            .is_some_and(|room| room.stats.is_silent(&self.0.sid()))
+    }
+
+    /// This track's level, from 0 to 1, reported 20 times a second while it's being
+    /// played, e.g. for per-participant speaking indicators.
+    pub fn audio_level_updates(&self) -> mpsc::UnboundedReceiver<f32> {
+        match self.1.upgrade() {
+            Some(room) => room.audio_levels.subscribe(self.0.rtc_track().id()),
+            None => mpsc::unbounded().1,
+        }
+    }
+}
+
+impl LocalAudioTrack {
+    /// The microphone's level, from 0 to 1, after echo cancellation and noise
+    /// suppression, reported 20 times a second while it's being captured.
+    pub fn audio_level_updates(&self) -> mpsc::UnboundedReceiver<f32> {
+        match self.1.upgrade() {
+            Some(room) => room.audio_levels.subscribe(self.0.rtc_track().id()),
+            None => mpsc::unbounded().1,
+        }
+    }
 }
 
 impl RemoteVideoTrack {
     pub fn sid(&self) -> TrackSid {
         self.0.sid()
     }
+
+    /// Marks how important this track is when the room has to shed load. LiveKit's
+    /// SDK has no per-subscription priority, so this guides the room's own decisions
+    /// instead, e.g. high priority tracks are never downgraded on decode overload.
+    pub fn set_priority(&self, priority: TrackPriority) {
+        if let Some(room) = self.1.upgrade() {
+            room.remote_track_settings
+                .lock()
+                .set_priority(&self.0.sid(), priority);
+        }
+    }
+
+    pub fn priority(&self) -> TrackPriority {
+        self.1
+            .upgrade()
+            .map_or_else(TrackPriority::default, |room| {
+                room.remote_track_settings.lock().priority(&self.0.sid())
+            })
+    }
+
+    /// The codec parameters of the encoding being received, as of the last stats
+    /// poll. `None` until the track has been polled at least once.
+    pub fn rtp_parameters(&self) -> Option<RtpParameters> {
+        self.1.upgrade()?.stats.rtp_parameters(&self.0.sid())
+    }
+
+    /// The video encodings received on this track over the last stats interval,
+    /// for checking which layer is actually arriving when a track is stuck at a
+    /// low quality. Empty until the track has been polled twice.
+    pub fn layer_stats(&self) -> Vec<LayerStats> {
+        self.1
+            .upgrade()
+            .map_or_else(Vec::new, |room| room.stats.layer_stats(&self.0.sid()))
+    }
+
+    /// Bitrate, loss, jitter, frame rate and resolution for this track, as of the
+    /// last stats poll. `None` until the first poll after subscribing, and the
+    /// bitrate stays zero until the second.
+    pub fn subscription_stats(&self) -> Option<SubscriptionStats> {
+        self.1.upgrade()?.stats.subscription_stats(&self.0.sid())
+    }
+
+    /// Delivers this track's frames as they're decoded, along with their size and
+    /// timestamp, for apps doing their own rendering or latency telemetry. Frames
+    /// stop being delivered when the task is dropped.
+    pub fn add_renderer(
+        &self,
+        mut callback: impl FnMut(TimedVideoFrame) + Send + 'static,
+        cx: &App,
+    ) -> Task<()> {
+        let frames = play_timed_remote_video_track(self);
+        cx.background_executor().spawn(async move {
+            let mut frames = std::pin::pin!(frames);
+            while let Some(frame) = frames.next().await {
+                callback(frame);
+            }
+        })
+    }
+
+    /// The bytes received on this track since it was subscribed, as of the last
+    /// stats poll. This keeps counting across reconnects and resubscriptions for
+    /// as long as the room stays connected.
+    pub fn total_bytes_received(&self) -> u64 {
+        self.1.upgrade().map_or(0, |room| {
+            room.stats.total_track_stats(&self.0.sid()).bytes_received
+        })
+    }
+
+    /// How far this track is out of sync with its publisher's microphone audio, as
+    /// of the last stats poll, regardless of which one is ahead. `None` if the
+    /// publisher isn't sending audio or it hasn't been polled yet.
+    pub fn av_sync_offset(&self) -> Option<Duration> {
+        self.1.upgrade()?.stats.av_sync_offset(&self.0.sid())
+    }
+
+    /// Whether this track is being decoded in hardware, as of the last stats poll.
+    /// `None` until the track has been polled at least once.
+    pub fn is_hardware_accelerated(&self) -> Option<bool> {
+        self.1
+            .upgrade()?
+            .stats
+            .is_hardware_accelerated(&self.0.sid())
+    }
+
+    /// Records the frames received on this track to an MP4 file at `path`, until
+    /// [`RecordingHandle::stop`] is called. Only video is recorded.
+    pub fn start_recording(&self, path: &Path, cx: &App) -> Result<RecordingHandle> {
+        if cfg!(not(target_os = "macos")) {
+            anyhow::bail!("recording is only supported on macOS");
+        }
+        Ok(recording::start_recording(
+            self,
+            path.to_path_buf(),
+            cx.background_executor(),
+        ))
+    }
 }
 
 impl RemoteTrackPublication {
@@ -233,12 +1794,20 @@ impl RemoteTrackPublication {
         self.0.is_muted()
     }
 
+    /// The MIME type of the encoding the server is forwarding to us, which is the
+    /// publisher's backup codec when we can't decode its primary one.
+    pub fn mime_type(&self) -> String {
+        self.0.mime_type()
+    }
+
     pub fn is_enabled(&self) -> bool {
         self.0.is_enabled()
     }
 
     pub fn track(&self) -> Option<RemoteTrack> {
-        self.0.track().map(remote_track_from_livekit)
+        self.0
+            .track()
+            .map(|track| remote_track_from_livekit(track, &self.1))
     }
 
     pub fn is_audio(&self) -> bool {
@@ -250,6 +1819,78 @@ impl RemoteTrackPublication {
         Tokio::spawn(cx, async move { track.set_enabled(enabled) }).detach();
     }
 
+    /// Starts or stops receiving this track. Unlike [`Self::set_enabled`], which only
+    /// pauses delivery, unsubscribing tears the track down entirely; it's reported
+    /// through `TrackSubscribed` and `TrackUnsubscribed` events as usual.
+    pub fn set_subscribed(&self, subscribed: bool, cx: &App) {
+        let publication = self.0.clone();
+        Tokio::spawn(cx, async move { publication.set_subscribed(subscribed) }).detach();
+    }
+
+    pub fn is_subscribed(&self) -> bool {
+        self.0.is_subscribed()
+    }
+
+    /// Receives this video track at the given quality until [`Self::unpin_quality`]
+    /// is called, e.g. while its tile is focused. Pinned tracks are never downgraded
+    /// automatically, such as on decode overload.
+    pub fn pin_quality(&self, quality: VideoQuality, cx: &App) {
+        if let Some(room) = self.1.upgrade() {
+            room.remote_track_settings
+                .lock()
+                .pinned_qualities
+                .insert(self.0.sid(), quality);
+        }
+        let publication = self.0.clone();
+        Tokio::spawn(
+            cx,
+            async move { publication.set_video_quality(quality.into()) },
+        )
+        .detach();
+    }
+
+    /// Requests this video track at the given quality, e.g. the one that matches the
+    /// size it's shown at. Unlike [`Self::pin_quality`], this leaves the room free to
+    /// lower it later on, such as on decode overload. Pinned tracks are left alone.
+    pub fn set_preferred_quality(&self, quality: VideoQuality, cx: &App) {
+        if self.pinned_quality().is_some() {
+            return;
+        }
+        let publication = self.0.clone();
+        Tokio::spawn(
+            cx,
+            async move { publication.set_video_quality(quality.into()) },
+        )
+        .detach();
+    }
+
+    /// Returns this track to automatic quality selection, starting from the highest
+    /// quality.
+    pub fn unpin_quality(&self, cx: &App) {
+        let unpinned = self.1.upgrade().is_some_and(|room| {
+            room.remote_track_settings
+                .lock()
+                .pinned_qualities
+                .remove(&self.0.sid())
+                .is_some()
+        });
+        if unpinned {
+            let publication = self.0.clone();
+            Tokio::spawn(cx, async move {
+                publication.set_video_quality(livekit::track::VideoQuality::High)
+            })
+            .detach();
+        }
+    }
+
+    pub fn pinned_quality(&self) -> Option<VideoQuality> {
+        self.1
+            .upgrade()?
+            .remote_track_settings
+            .lock()
+            .pinned_quality(&self.0.sid())
+    }
+
     pub fn sid(&self) -> TrackSid {
         self.0.sid()
     }
@@ -285,50 +1926,124 @@ impl Participant {
     }
 }
 
-fn participant_from_livekit(participant: livekit::participant::Participant) -> Participant {
+impl From<VideoCodec> for livekit::options::VideoCodec {
+    fn from(codec: VideoCodec) -> Self {
+        match codec {
+            VideoCodec::VP8 => livekit::options::VideoCodec::VP8,
+            VideoCodec::VP9 => livekit::options::VideoCodec::VP9,
+            VideoCodec::H264 => livekit::options::VideoCodec::H264,
+            VideoCodec::AV1 => livekit::options::VideoCodec::AV1,
+        }
+    }
+}
+
+impl From<VideoQuality> for livekit::track::VideoQuality {
+    fn from(quality: VideoQuality) -> Self {
+        match quality {
+            VideoQuality::Low => livekit::track::VideoQuality::Low,
+            VideoQuality::Medium => livekit::track::VideoQuality::Medium,
+            VideoQuality::High => livekit::track::VideoQuality::High,
+        }
+    }
+}
+
+impl From<livekit::participant::ConnectionQuality> for ConnectionQuality {
+    fn from(quality: livekit::participant::ConnectionQuality) -> Self {
+        match quality {
+            livekit::participant::ConnectionQuality::Excellent => ConnectionQuality::Excellent,
+            livekit::participant::ConnectionQuality::Good => ConnectionQuality::Good,
+            livekit::participant::ConnectionQuality::Poor => ConnectionQuality::Poor,
+            livekit::participant::ConnectionQuality::Lost => ConnectionQuality::Lost,
+        }
+    }
+}
+
+impl From<livekit::track::TrackSource> for TrackSource {
+    fn from(source: livekit::track::TrackSource) -> Self {
+        match source {
+            livekit::track::TrackSource::Unknown => TrackSource::Unknown,
+            livekit::track::TrackSource::Camera => TrackSource::Camera,
+            livekit::track::TrackSource::Microphone => TrackSource::Microphone,
+            livekit::track::TrackSource::Screenshare => TrackSource::Screenshare,
+            livekit::track::TrackSource::ScreenshareAudio => TrackSource::ScreenshareAudio,
+        }
+    }
+}
+
+impl From<TrackSource> for livekit::track::TrackSource {
+    fn from(source: TrackSource) -> Self {
+        match source {
+            TrackSource::Unknown => livekit::track::TrackSource::Unknown,
+            TrackSource::Camera => livekit::track::TrackSource::Camera,
+            TrackSource::Microphone => livekit::track::TrackSource::Microphone,
+            TrackSource::Screenshare => livekit::track::TrackSource::Screenshare,
+            TrackSource::ScreenshareAudio => livekit::track::TrackSource::ScreenshareAudio,
+        }
+    }
+}
+
+fn participant_from_livekit(
+    participant: livekit::participant::Participant,
+    room: &Weak<RoomState>,
+) -> Participant {
     match participant {
         livekit::participant::Participant::Local(local) => {
-            Participant::Local(LocalParticipant(local))
+            Participant::Local(LocalParticipant(local, room.clone()))
         }
         livekit::participant::Participant::Remote(remote) => {
-            Participant::Remote(RemoteParticipant(remote))
+            Participant::Remote(RemoteParticipant(remote, room.clone()))
         }
     }
 }
 
 fn publication_from_livekit(
     publication: livekit::publication::TrackPublication,
+    room: &Weak<RoomState>,
 ) -> TrackPublication {
     match publication {
         livekit::publication::TrackPublication::Local(local) => {
             TrackPublication::Local(LocalTrackPublication(local))
         }
         livekit::publication::TrackPublication::Remote(remote) => {
-            TrackPublication::Remote(RemoteTrackPublication(remote))
+            TrackPublication::Remote(RemoteTrackPublication(remote, room.clone()))
         }
     }
 }
 
-fn remote_track_from_livekit(track: livekit::track::RemoteTrack) -> RemoteTrack {
+fn remote_track_from_livekit(
+    track: livekit::track::RemoteTrack,
+    room: &Weak<RoomState>,
+) -> RemoteTrack {
     match track {
-        livekit::track::RemoteTrack::Audio(audio) => RemoteTrack::Audio(RemoteAudioTrack(audio)),
-        livekit::track::RemoteTrack::Video(video) => RemoteTrack::Video(RemoteVideoTrack(video)),
+        livekit::track::RemoteTrack::Audio(audio) => {
+            RemoteTrack::Audio(RemoteAudioTrack(audio, room.clone()))
+        }
+        livekit::track::RemoteTrack::Video(video) => {
+            RemoteTrack::Video(RemoteVideoTrack(video, room.clone()))
+        }
     }
 }
 
-fn local_track_from_livekit(track: livekit::track::LocalTrack) -> LocalTrack {
+fn local_track_from_livekit(
+    track: livekit::track::LocalTrack,
+    room: &Weak<RoomState>,
+) -> LocalTrack {
     match track {
-        livekit::track::LocalTrack::Audio(audio) => LocalTrack::Audio(LocalAudioTrack(audio)),
-        livekit::track::LocalTrack::Video(video) => LocalTrack::Video(LocalVideoTrack(video)),
+        livekit::track::LocalTrack::Audio(audio) => {
+            LocalTrack::Audio(LocalAudioTrack(audio, room.clone()))
+        }
+        livekit::track::LocalTrack::Video(video) => {
+            LocalTrack::Video(LocalVideoTrack::new(video, room.clone()))
+        }
     }
 }
-fn room_event_from_livekit(event: livekit::RoomEvent) -> Option<RoomEvent> {
+fn room_event_from_livekit(event: livekit::RoomEvent, room: &Weak<RoomState>) -> Option<RoomEvent> {
     let event = match event {
         livekit::RoomEvent::ParticipantConnected(remote_participant) => {
-            RoomEvent::ParticipantConnected(RemoteParticipant(remote_participant))
+            RoomEvent::ParticipantConnected(RemoteParticipant(remote_participant, room.clone()))
         }
         livekit::RoomEvent::ParticipantDisconnected(remote_participant) => {
-            RoomEvent::ParticipantDisconnected(RemoteParticipant(remote_participant))
+            RoomEvent::ParticipantDisconnected(RemoteParticipant(remote_participant, room.clone()))
         }
         livekit::RoomEvent::LocalTrackPublished {
             publication,
@@ -336,72 +2051,72 @@ fn room_event_from_livekit(event: livekit::RoomEvent) -> Option<RoomEvent> {
             participant,
         } => RoomEvent::LocalTrackPublished {
             publication: LocalTrackPublication(publication),
-            track: local_track_from_livekit(track),
-            participant: LocalParticipant(participant),
+            track: local_track_from_livekit(track, room),
+            participant: LocalParticipant(participant, room.clone()),
         },
         livekit::RoomEvent::LocalTrackUnpublished {
             publication,
             participant,
         } => RoomEvent::LocalTrackUnpublished {
             publication: LocalTrackPublication(publication),
-            participant: LocalParticipant(participant),
+            participant: LocalParticipant(participant, room.clone()),
         },
         livekit::RoomEvent::LocalTrackSubscribed { track } => RoomEvent::LocalTrackSubscribed {
-            track: local_track_from_livekit(track),
+            track: local_track_from_livekit(track, room),
         },
         livekit::RoomEvent::TrackSubscribed {
             track,
             publication,
             participant,
         } => RoomEvent::TrackSubscribed {
-            track: remote_track_from_livekit(track),
-            publication: RemoteTrackPublication(publication),
-            participant: RemoteParticipant(participant),
+            track: remote_track_from_livekit(track, room),
+            publication: RemoteTrackPublication(publication, room.clone()),
+            participant: RemoteParticipant(participant, room.clone()),
         },
         livekit::RoomEvent::TrackUnsubscribed {
             track,
             publication,
             participant,
         } => RoomEvent::TrackUnsubscribed {
-            track: remote_track_from_livekit(track),
-            publication: RemoteTrackPublication(publication),
-            participant: RemoteParticipant(participant),
+            track: remote_track_from_livekit(track, room),
+            publication: RemoteTrackPublication(publication, room.clone()),
+            participant: RemoteParticipant(participant, room.clone()),
         },
         livekit::RoomEvent::TrackSubscriptionFailed {
             participant,
             error: _,
             track_sid,
         } => RoomEvent::TrackSubscriptionFailed {
-            participant: RemoteParticipant(participant),
+            participant: RemoteParticipant(participant, room.clone()),
             track_sid,
         },
         livekit::RoomEvent::TrackPublished {
             publication,
             participant,
         } => RoomEvent::TrackPublished {
-            publication: RemoteTrackPublication(publication),
-            participant: RemoteParticipant(participant),
+            publication: RemoteTrackPublication(publication, room.clone()),
+            participant: RemoteParticipant(participant, room.clone()),
         },
         livekit::RoomEvent::TrackUnpublished {
             publication,
             participant,
         } => RoomEvent::TrackUnpublished {
-            publication: RemoteTrackPublication(publication),
-            participant: RemoteParticipant(participant),
+            publication: RemoteTrackPublication(publication, room.clone()),
+            participant: RemoteParticipant(participant, room.clone()),
         },
         livekit::RoomEvent::TrackMuted {
             participant,
             publication,
         } => RoomEvent::TrackMuted {
-            publication: publication_from_livekit(publication),
-            participant: participant_from_livekit(participant),
+            publication: publication_from_livekit(publication, room),
+            participant: participant_from_livekit(participant, room),
         },
         livekit::RoomEvent::TrackUnmuted {
             participant,
             publication,
         } => RoomEvent::TrackUnmuted {
-            publication: publication_from_livekit(publication),
-            participant: participant_from_livekit(participant),
+            publication: publication_from_livekit(publication, room),
+            participant: participant_from_livekit(participant, room),
         },
         livekit::RoomEvent::RoomMetadataChanged {
             old_metadata,
@@ -415,7 +2130,7 @@ fn room_event_from_livekit(event: livekit::RoomEvent) -> Option<RoomEvent> {
             old_metadata,
             metadata,
         } => RoomEvent::ParticipantMetadataChanged {
-            participant: participant_from_livekit(participant),
+            participant: participant_from_livekit(participant, room),
             old_metadata,
             metadata,
         },
@@ -424,7 +2139,7 @@ fn room_event_from_livekit(event: livekit::RoomEvent) -> Option<RoomEvent> {
             old_name,
             name,
         } => RoomEvent::ParticipantNameChanged {
-            participant: participant_from_livekit(participant),
+            participant: participant_from_livekit(participant, room),
             old_name,
             name,
         },
@@ -432,12 +2147,15 @@ fn room_event_from_livekit(event: livekit::RoomEvent) -> Option<RoomEvent> {
             participant,
             changed_attributes,
         } => RoomEvent::ParticipantAttributesChanged {
-            participant: participant_from_livekit(participant),
+            participant: participant_from_livekit(participant, room),
             changed_attributes: changed_attributes.into_iter().collect(),
         },
         livekit::RoomEvent::ActiveSpeakersChanged { speakers } => {
             RoomEvent::ActiveSpeakersChanged {
-                speakers: speakers.into_iter().map(participant_from_livekit).collect(),
+                speakers: speakers
+                    .into_iter()
+                    .map(|speaker| participant_from_livekit(speaker, room))
+                    .collect(),
             }
         }
         livekit::RoomEvent::Connected {
@@ -448,8 +2166,12 @@ fn room_event_from_livekit(event: livekit::RoomEvent) -> Option<RoomEvent> {
                 .map({
                     |(p, t)| {
                         (
-                            RemoteParticipant(p),
-                            t.into_iter().map(RemoteTrackPublication).collect(),
+                            RemoteParticipant(p, room.clone()),
+                            t.into_iter()
+                                .map(|publication| {
+                                    RemoteTrackPublication(publication, room.clone())
+                                })
+                                .collect(),
                         )
                     }
                 })
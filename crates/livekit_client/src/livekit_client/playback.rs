@@ -9,6 +9,7 @@ use gpui::{
 use libwebrtc::native::{apm, audio_mixer, audio_resampler};
 use livekit::track;
 
+use collections::HashMap;
 use livekit::webrtc::{
     audio_frame::AudioFrame,
     audio_source::{AudioSourceOptions, RtcAudioSource, native::NativeAudioSource},
@@ -21,16 +22,40 @@ use parking_lot::Mutex;
 use std::cell::RefCell;
 use std::sync::Weak;
 use std::sync::atomic::{self, AtomicI32};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::{borrow::Cow, collections::VecDeque, sync::Arc, thread};
 use util::{ResultExt as _, maybe};
 
+use crate::loopback_probe::{self, ProbeDetector};
+use crate::{RoomEvent, fit_within};
+
 pub(crate) struct AudioStack {
     executor: BackgroundExecutor,
     apm: Arc<Mutex<apm::AudioProcessingModule>>,
     mixer: Arc<Mutex<audio_mixer::AudioMixer>>,
     _output_task: RefCell<Weak<Task<()>>>,
+    /// Outputs for tracks routed away from the default device, keyed by device name.
+    device_outputs: RefCell<HashMap<String, (Arc<Mutex<audio_mixer::AudioMixer>>, Weak<Task<()>>)>>,
+    /// The device chosen for each remote track, by track sid, along with where the
+    /// track is currently playing if it is. Entries are removed once the track's
+    /// stream is dropped.
+    track_routes: Arc<Mutex<HashMap<String, TrackRoute>>>,
     next_ssrc: AtomicI32,
+    events: UnboundedSender<RoomEvent>,
+    audio_levels: Arc<AudioLevelListeners>,
+}
+
+#[derive(Default)]
+struct TrackRoute {
+    device_name: Option<String>,
+    playing: Weak<Mutex<PlayingRoute>>,
+}
+
+/// The mixer a playing remote audio track's source is currently added to.
+struct PlayingRoute {
+    source: AudioMixerSource,
+    mixer: Arc<Mutex<audio_mixer::AudioMixer>>,
+    _output_task: Arc<Task<()>>,
 }
 
 // NOTE: We use WebRTC's mixer which only supports
@@ -40,8 +65,15 @@ pub(crate) struct AudioStack {
 const SAMPLE_RATE: u32 = 48000;
 const NUM_CHANNELS: u32 = 2;
 
+const LOOPBACK_TIMEOUT: Duration = Duration::from_secs(3);
+const THUMBNAIL_TIMEOUT: Duration = Duration::from_secs(2);
+
 impl AudioStack {
-    pub(crate) fn new(executor: BackgroundExecutor) -> Self {
+    pub(crate) fn new(
+        executor: BackgroundExecutor,
+        events: UnboundedSender<RoomEvent>,
+        audio_levels: Arc<AudioLevelListeners>,
+    ) -> Self {
         let apm = Arc::new(Mutex::new(apm::AudioProcessingModule::new(
             true, true, true, true,
         )));
@@ -51,7 +83,11 @@ impl AudioStack {
             apm,
             mixer,
             _output_task: RefCell::new(Weak::new()),
+            device_outputs: RefCell::default(),
+            track_routes: Arc::default(),
             next_ssrc: AtomicI32::new(1),
+            events,
+            audio_levels,
         }
     }
 
@@ -59,8 +95,6 @@ impl AudioStack {
         &self,
         track: &livekit::track::RemoteAudioTrack,
     ) -> AudioStream {
-        let output_task = self.start_output();
-
         let next_ssrc = self.next_ssrc.fetch_add(1, atomic::Ordering::Relaxed);
         let source = AudioMixerSource {
             ssrc: next_ssrc,
@@ -68,7 +102,18 @@ impl AudioStack {
             num_channels: NUM_CHANNELS,
             buffer: Arc::default(),
         };
-        self.mixer.lock().add_source(source.clone());
+        let track_sid = track.sid().to_string();
+        let mut track_routes = self.track_routes.lock();
+        let track_route = track_routes.entry(track_sid.clone()).or_default();
+        let (mixer, output_task) = self.output_for(track_route.device_name.as_deref());
+        mixer.lock().add_source(source.clone());
+        let route = Arc::new(Mutex::new(PlayingRoute {
+            source: source.clone(),
+            mixer,
+            _output_task: output_task,
+        }));
+        track_route.playing = Arc::downgrade(&route);
+        drop(track_routes);
 
         let mut stream = NativeAudioStream::new(
             track.rtc_track(),
@@ -76,20 +121,32 @@ impl AudioStack {
             source.num_channels as i32,
         );
 
+        let mut level_meter =
+            AudioLevelMeter::new(track.rtc_track().id(), self.audio_levels.clone());
         let receive_task = self.executor.spawn({
             let source = source.clone();
             async move {
                 while let Some(frame) = stream.next().await {
+                    level_meter.measure(&frame.data);
                     source.receive(frame);
                 }
             }
         });
 
-        let mixer = self.mixer.clone();
+        let track_routes = self.track_routes.clone();
         let on_drop = util::defer(move || {
-            mixer.lock().remove_source(source.ssrc);
+            {
+                let route = route.lock();
+                route.mixer.lock().remove_source(route.source.ssrc);
+            }
             drop(receive_task);
-            drop(output_task);
+            let mut track_routes = track_routes.lock();
+            let is_current = track_routes.get(&track_sid).is_some_and(|track_route| {
+                Weak::ptr_eq(&track_route.playing, &Arc::downgrade(&route))
+            });
+            if is_current {
+                track_routes.remove(&track_sid);
+            }
         });
 
         AudioStream::Output {
@@ -97,9 +154,75 @@ impl AudioStack {
         }
     }
 
+    /// Plays a short probe through the default output device and waits for the
+    /// default input device to pick it up, returning the round-trip latency.
+    pub(crate) fn measure_loopback_latency(
+        &self,
+    ) -> impl Future<Output = Result<Duration>> + use<> {
+        let output_task = self.start_output();
+
+        let ssrc = self.next_ssrc.fetch_add(1, atomic::Ordering::Relaxed);
+        let probe = LoopbackProbeSource {
+            ssrc,
+            played_at: Arc::default(),
+        };
+        let played_at = probe.played_at.clone();
+        self.mixer.lock().add_source(probe);
+
+        // Echo cancellation would remove the very probe we're listening for, so the
+        // microphone is captured without any processing for the duration of the test.
+        let apm = Arc::new(Mutex::new(apm::AudioProcessingModule::new(
+            false, false, false, false,
+        )));
+        let (frame_tx, mut frame_rx) = futures::channel::mpsc::unbounded();
+        let capture_task = self.executor.spawn(async move {
+            Self::capture_input(apm, frame_tx, None, SAMPLE_RATE, NUM_CHANNELS).await
+        });
+
+        let mixer = self.mixer.clone();
+        let timeout = self.executor.timer(LOOPBACK_TIMEOUT);
+        async move {
+            let _cleanup = util::defer(move || {
+                mixer.lock().remove_source(ssrc);
+                drop(capture_task);
+                drop(output_task);
+            });
+
+            let detect = async {
+                let mut detector = ProbeDetector::new();
+                while let Some(frame) = frame_rx.next().await {
+                    let Some(played_at) = *played_at.lock() else {
+                        continue;
+                    };
+                    if let Some(samples_since_probe) =
+                        detector.push(&frame.data, frame.num_channels as usize)
+                    {
+                        // The frame was captured just now, so the probe was heard
+                        // as long ago as the samples captured since it started.
+                        let heard_ago = Duration::from_secs_f64(
+                            samples_since_probe as f64 / SAMPLE_RATE as f64,
+                        );
+                        return Ok(played_at.elapsed().saturating_sub(heard_ago));
+                    }
+                }
+                Err(anyhow!("microphone capture ended during loopback test"))
+            };
+            futures::pin_mut!(detect);
+
+            match futures::future::select(detect, timeout).await {
+                futures::future::Either::Left((result, _)) => result,
+                futures::future::Either::Right(_) => Err(anyhow!(
+                    "loopback probe was not heard within {:?}",
+                    LOOPBACK_TIMEOUT
+                )),
+            }
+        }
+    }
+
     pub(crate) fn capture_local_microphone_track(
         &self,
-    ) -> Result<(crate::LocalAudioTrack, AudioStream)> {
+        device_name: Option<String>,
+    ) -> Result<(track::LocalAudioTrack, AudioStream)> {
         let source = NativeAudioSource::new(
             // n.b. this struct's options are always ignored, noise cancellation is provided by apm.
             AudioSourceOptions::default(),
@@ -116,16 +239,19 @@ impl AudioStack {
         let apm = self.apm.clone();
 
         let (frame_tx, mut frame_rx) = futures::channel::mpsc::unbounded();
+        let mut level_meter =
+            AudioLevelMeter::new(track.rtc_track().id(), self.audio_levels.clone());
         let transmit_task = self.executor.spawn({
             let source = source.clone();
             async move {
                 while let Some(frame) = frame_rx.next().await {
+                    level_meter.measure(&frame.data);
                     source.capture_frame(&frame).await.log_err();
                 }
             }
         });
         let capture_task = self.executor.spawn(async move {
-            Self::capture_input(apm, frame_tx, SAMPLE_RATE, NUM_CHANNELS).await
+            Self::capture_input(apm, frame_tx, device_name, SAMPLE_RATE, NUM_CHANNELS).await
         });
 
         let on_drop = util::defer(|| {
@@ -133,13 +259,71 @@ impl AudioStack {
             drop(capture_task);
         });
         return Ok((
-            super::LocalAudioTrack(track),
+            track,
             AudioStream::Output {
                 _drop: Box::new(on_drop),
             },
         ));
     }
 
+    /// Plays a remote track through the named output device rather than the default
+    /// one, or back through the default one when `None`. This is remembered for the
+    /// track and takes effect immediately if it's already playing.
+    pub(crate) fn set_output_device(&self, track_sid: &str, device_name: Option<String>) {
+        let mut track_routes = self.track_routes.lock();
+        let track_route = track_routes.entry(track_sid.to_string()).or_default();
+        if track_route.device_name == device_name {
+            return;
+        }
+        track_route.device_name = device_name;
+        if let Some(route) = track_route.playing.upgrade() {
+            let (mixer, output_task) = self.output_for(track_route.device_name.as_deref());
+            let mut route = route.lock();
+            route.mixer.lock().remove_source(route.source.ssrc);
+            mixer.lock().add_source(route.source.clone());
+            route.mixer = mixer;
+            route._output_task = output_task;
+        }
+    }
+
+    fn output_for(
+        &self,
+        device_name: Option<&str>,
+    ) -> (Arc<Mutex<audio_mixer::AudioMixer>>, Arc<Task<()>>) {
+        let Some(device_name) = device_name else {
+            return (self.mixer.clone(), self.start_output());
+        };
+        let mut device_outputs = self.device_outputs.borrow_mut();
+        if let Some((mixer, task)) = device_outputs.get(device_name) {
+            if let Some(task) = task.upgrade() {
+                return (mixer.clone(), task);
+            }
+        }
+        let mixer = Arc::new(Mutex::new(audio_mixer::AudioMixer::new()));
+        let task = Arc::new(self.executor.spawn({
+            let mixer = mixer.clone();
+            let device_name = device_name.to_string();
+            let apm = self.apm.clone();
+            async move {
+                Self::play_output(
+                    Some(apm),
+                    mixer,
+                    None,
+                    Some(device_name),
+                    SAMPLE_RATE,
+                    NUM_CHANNELS,
+                )
+                .await
+                .log_err();
+            }
+        }));
+        device_outputs.insert(
+            device_name.to_string(),
+            (mixer.clone(), Arc::downgrade(&task)),
+        );
+        (mixer, task)
+    }
+
     fn start_output(&self) -> Arc<Task<()>> {
         if let Some(task) = self._output_task.borrow().upgrade() {
             return task;
@@ -147,25 +331,62 @@ impl AudioStack {
         let task = Arc::new(self.executor.spawn({
             let apm = self.apm.clone();
             let mixer = self.mixer.clone();
+            let events = self.events.clone();
             async move {
-                Self::play_output(apm, mixer, SAMPLE_RATE, NUM_CHANNELS)
-                    .await
-                    .log_err();
+                Self::play_output(
+                    Some(apm),
+                    mixer,
+                    Some(events),
+                    None,
+                    SAMPLE_RATE,
+                    NUM_CHANNELS,
+                )
+                .await
+                .log_err();
             }
         }));
         *self._output_task.borrow_mut() = Arc::downgrade(&task);
         task
     }
 
+    /// Plays the mixer's output through the named device, or the default one. Every
+    /// output is fed to echo cancellation, since the microphone can pick up any of
+    /// them, but only the default one is reported through `events`.
     async fn play_output(
-        apm: Arc<Mutex<apm::AudioProcessingModule>>,
+        apm: Option<Arc<Mutex<apm::AudioProcessingModule>>>,
         mixer: Arc<Mutex<audio_mixer::AudioMixer>>,
+        events: Option<UnboundedSender<RoomEvent>>,
+        device_name: Option<String>,
         sample_rate: u32,
         num_channels: u32,
     ) -> Result<()> {
+        let mut current_device = None;
         loop {
             let mut device_change_listener = DeviceChangeListener::new(false)?;
-            let (output_device, output_config) = default_device(false)?;
+            // A named device that has disappeared falls back to the default until it
+            // comes back, which is checked again on every device change.
+            let (output_device, output_config) =
+                match device_name.as_deref().map(output_device_named) {
+                    Some(Ok(device)) => device,
+                    _ => default_device(false)?,
+                };
+            // Playback otherwise follows the system default, so when the device in use
+            // disappears the OS picks a new default and we switch to it here.
+            let device = output_device.name().ok();
+            if device != current_device {
+                log::info!(
+                    "Using speaker: {}",
+                    device.as_deref().unwrap_or("<unknown>")
+                );
+                if let Some(events) = &events {
+                    events
+                        .unbounded_send(RoomEvent::AudioOutputChanged {
+                            device: device.clone(),
+                        })
+                        .ok();
+                }
+                current_device = device;
+            }
             let (end_on_drop_tx, end_on_drop_rx) = std::sync::mpsc::channel::<()>();
             let mixer = mixer.clone();
             let apm = apm.clone();
@@ -201,13 +422,15 @@ impl AudioStack {
                                     output_config.sample_rate().0,
                                 );
                                 buf = sampled.to_vec();
-                                apm.lock()
-                                    .process_reverse_stream(
-                                        &mut buf,
-                                        output_config.sample_rate().0 as i32,
-                                        output_config.channels() as i32,
-                                    )
-                                    .ok();
+                                if let Some(apm) = &apm {
+                                    apm.lock()
+                                        .process_reverse_stream(
+                                            &mut buf,
+                                            output_config.sample_rate().0 as i32,
+                                            output_config.channels() as i32,
+                                        )
+                                        .ok();
+                                }
                             }
                         }
                     },
@@ -232,12 +455,22 @@ impl AudioStack {
     async fn capture_input(
         apm: Arc<Mutex<apm::AudioProcessingModule>>,
         frame_tx: UnboundedSender<AudioFrame<'static>>,
+        device_name: Option<String>,
         sample_rate: u32,
         num_channels: u32,
     ) -> Result<()> {
         loop {
             let mut device_change_listener = DeviceChangeListener::new(true)?;
-            let (device, config) = default_device(true)?;
+            // As with playback, a named device that has disappeared falls back to the
+            // default until it comes back.
+            let (device, config) = match device_name.as_deref().map(input_device_named) {
+                Some(Ok(device)) => device,
+                Some(Err(error)) => {
+                    log::warn!("capturing from the default microphone instead: {error:#}");
+                    default_device(true)?
+                }
+                None => default_device(true)?,
+            };
             let (end_on_drop_tx, end_on_drop_rx) = std::sync::mpsc::channel::<()>();
             let apm = apm.clone();
             let frame_tx = frame_tx.clone();
@@ -315,8 +548,6 @@ impl AudioStack {
     }
 }
 
-use super::LocalVideoTrack;
-
 pub enum AudioStream {
     Input { _task: Task<()> },
     Output { _drop: Box<dyn std::any::Any> },
@@ -324,14 +555,17 @@ pub enum AudioStream {
 
 pub(crate) async fn capture_local_video_track(
     capture_source: &dyn ScreenCaptureSource,
+    max_resolution: Option<(u32, u32)>,
     cx: &mut gpui::AsyncApp,
-) -> Result<(crate::LocalVideoTrack, Box<dyn ScreenCaptureStream>)> {
+) -> Result<(track::LocalVideoTrack, Box<dyn ScreenCaptureStream>)> {
     let resolution = capture_source.resolution()?;
+    let capture_size = (resolution.width.0 as u32, resolution.height.0 as u32);
+    let scaled_size = max_resolution
+        .map(|max_resolution| fit_within(capture_size, max_resolution))
+        .filter(|scaled_size| *scaled_size != capture_size);
+    let (width, height) = scaled_size.unwrap_or(capture_size);
     let track_source = gpui_tokio::Tokio::spawn(cx, async move {
-        NativeVideoSource::new(VideoResolution {
-            width: resolution.width.0 as u32,
-            height: resolution.height.0 as u32,
-        })
+        NativeVideoSource::new(VideoResolution { width, height })
     })?
     .await?;
 
@@ -339,26 +573,145 @@ pub(crate) async fn capture_local_video_track(
         .stream({
             let track_source = track_source.clone();
             Box::new(move |frame| {
-                if let Some(buffer) = video_frame_buffer_to_webrtc(frame) {
-                    track_source.capture_frame(&VideoFrame {
+                let Some(buffer) = video_frame_buffer_to_webrtc(frame) else {
+                    return;
+                };
+                match scaled_size {
+                    Some((width, height)) => track_source.capture_frame(&VideoFrame {
+                        rotation: VideoRotation::VideoRotation0,
+                        timestamp_us: 0,
+                        buffer: buffer.as_ref().to_i420().scale(width as i32, height as i32),
+                    }),
+                    None => track_source.capture_frame(&VideoFrame {
                         rotation: VideoRotation::VideoRotation0,
                         timestamp_us: 0,
                         buffer,
-                    });
+                    }),
                 }
             })
         })
         .await??;
 
     Ok((
-        LocalVideoTrack(track::LocalVideoTrack::create_video_track(
+        track::LocalVideoTrack::create_video_track(
             "screen share",
             RtcVideoSource::Native(track_source),
-        )),
+        ),
         capture_stream,
     ))
 }
 
+/// Captures a single frame from `capture_source`, scaled down to fit within
+/// `max_size`, e.g. to show previews in a screen share picker. Gives up after
+/// [`THUMBNAIL_TIMEOUT`], since some sources, such as minimized windows, never
+/// produce a frame.
+pub(crate) async fn capture_thumbnail(
+    capture_source: &dyn ScreenCaptureSource,
+    max_size: (u32, u32),
+    executor: &BackgroundExecutor,
+) -> Result<RemoteVideoFrame> {
+    let capture = async {
+        let (frame_tx, frame_rx) = futures::channel::oneshot::channel();
+        let frame_tx = Mutex::new(Some(frame_tx));
+        let _capture_stream = capture_source
+            .stream(Box::new(move |frame| {
+                let Some(buffer) = video_frame_buffer_to_webrtc(frame) else {
+                    return;
+                };
+                let buffer = buffer.as_ref();
+                let (width, height) = fit_within((buffer.width(), buffer.height()), max_size);
+                let thumbnail = buffer.to_i420().scale(width as i32, height as i32);
+                if let Some(frame_tx) = frame_tx.lock().take() {
+                    frame_tx.send(thumbnail).ok();
+                }
+            }))
+            .await?
+            .context("screen capture source is no longer available")?;
+        frame_rx
+            .await
+            .context("screen capture ended before producing a frame")
+    };
+    futures::pin_mut!(capture);
+    let timeout = executor.timer(THUMBNAIL_TIMEOUT);
+    let thumbnail = match futures::future::select(capture, timeout).await {
+        futures::future::Either::Left((thumbnail, _)) => thumbnail?,
+        futures::future::Either::Right(_) => anyhow::bail!(
+            "screen capture did not produce a frame within {:?}",
+            THUMBNAIL_TIMEOUT
+        ),
+    };
+
+    #[cfg(target_os = "macos")]
+    let frame = video_frame_buffer_from_webrtc(
+        create_buffer_pool(thumbnail.width(), thumbnail.height())?,
+        Box::new(thumbnail),
+    );
+    #[cfg(not(target_os = "macos"))]
+    let frame = video_frame_buffer_from_webrtc(Box::new(thumbnail));
+    frame.ok_or_else(|| anyhow!("failed to convert screen capture frame"))
+}
+
+/// Captures from the given microphone, or the default one, without publishing
+/// anything, and reports its level (from 0 to 1) for every 10ms of audio.
+pub(crate) fn preview_microphone(
+    executor: &BackgroundExecutor,
+    device_name: Option<String>,
+) -> (Task<()>, futures::channel::mpsc::UnboundedReceiver<f32>) {
+    let apm = Arc::new(Mutex::new(apm::AudioProcessingModule::new(
+        true, true, true, true,
+    )));
+    let (frame_tx, mut frame_rx) = futures::channel::mpsc::unbounded();
+    let (level_tx, level_rx) = futures::channel::mpsc::unbounded();
+    let capture_task = executor.spawn(async move {
+        AudioStack::capture_input(apm, frame_tx, device_name, SAMPLE_RATE, NUM_CHANNELS).await
+    });
+    let task = executor.spawn(async move {
+        let _capture_task = capture_task;
+        while let Some(frame) = frame_rx.next().await {
+            if level_tx.unbounded_send(audio_level(&frame.data)).is_err() {
+                break;
+            }
+        }
+    });
+    (task, level_rx)
+}
+
+/// The RMS level of the given samples, from 0 (silence) to 1 (full scale).
+fn audio_level(samples: &[i16]) -> f32 {
+    if samples.is_empty() {
+        return 0.;
+    }
+    let sum_of_squares = samples
+        .iter()
+        .map(|sample| (*sample as f32 / i16::MAX as f32).powi(2))
+        .sum::<f32>();
+    (sum_of_squares / samples.len() as f32).sqrt()
+}
+
+fn input_device_named(name: &str) -> Result<(cpal::Device, cpal::SupportedStreamConfig)> {
+    let device = cpal::default_host()
+        .input_devices()
+        .context("failed to enumerate audio input devices")?
+        .find(|device| device.name().is_ok_and(|device_name| device_name == name))
+        .ok_or_else(|| anyhow!("no audio input device named {:?}", name))?;
+    let config = device
+        .default_input_config()
+        .context("failed to get default input config")?;
+    Ok((device, config))
+}
+
+fn output_device_named(name: &str) -> Result<(cpal::Device, cpal::SupportedStreamConfig)> {
+    let device = cpal::default_host()
+        .output_devices()
+        .context("failed to enumerate audio output devices")?
+        .find(|device| device.name().is_ok_and(|device_name| device_name == name))
+        .ok_or_else(|| anyhow!("no audio output device named {:?}", name))?;
+    let config = device
+        .default_output_config()
+        .context("failed to get default output config")?;
+    Ok((device, config))
+}
+
 fn default_device(input: bool) -> Result<(cpal::Device, cpal::SupportedStreamConfig)> {
     let device;
     let config;
@@ -380,6 +733,73 @@ fn default_device(input: bool) -> Result<(cpal::Device, cpal::SupportedStreamCon
     Ok((device, config))
 }
 
+/// Levels are reported once every this many 10ms frames, i.e. 20 times a second,
+/// which is plenty for a meter.
+const AUDIO_LEVEL_FRAMES: usize = 5;
+
+/// A room's listeners registered through `audio_level_updates`, keyed by the id of
+/// the WebRTC track whose audio they're measuring, since that's known before a local
+/// track is published. A track's listeners are removed once it's unsubscribed or
+/// unpublished.
+#[derive(Default)]
+pub(crate) struct AudioLevelListeners(Mutex<HashMap<String, Vec<UnboundedSender<f32>>>>);
+
+impl AudioLevelListeners {
+    pub(crate) fn subscribe(
+        &self,
+        rtc_track_id: String,
+    ) -> futures::channel::mpsc::UnboundedReceiver<f32> {
+        let (tx, rx) = futures::channel::mpsc::unbounded();
+        self.0.lock().entry(rtc_track_id).or_default().push(tx);
+        rx
+    }
+
+    pub(crate) fn remove(&self, rtc_track_id: &str) {
+        self.0.lock().remove(rtc_track_id);
+    }
+
+    fn report(&self, rtc_track_id: &str, level: f32) {
+        let mut all_txs = self.0.lock();
+        if let Some(txs) = all_txs.get_mut(rtc_track_id) {
+            txs.retain(|tx| tx.unbounded_send(level).is_ok());
+            if txs.is_empty() {
+                all_txs.remove(rtc_track_id);
+            }
+        }
+    }
+}
+
+/// Reports the peak level of each group of [`AUDIO_LEVEL_FRAMES`] frames to the
+/// track's level listeners.
+struct AudioLevelMeter {
+    rtc_track_id: String,
+    listeners: Arc<AudioLevelListeners>,
+    peak: f32,
+    frames: usize,
+}
+
+impl AudioLevelMeter {
+    fn new(rtc_track_id: String, listeners: Arc<AudioLevelListeners>) -> Self {
+        Self {
+            rtc_track_id,
+            listeners,
+            peak: 0.,
+            frames: 0,
+        }
+    }
+
+    fn measure(&mut self, samples: &[i16]) {
+        self.peak = self.peak.max(audio_level(samples));
+        self.frames += 1;
+        if self.frames < AUDIO_LEVEL_FRAMES {
+            return;
+        }
+        self.listeners.report(&self.rtc_track_id, self.peak);
+        self.peak = 0.;
+        self.frames = 0;
+    }
+}
+
 #[derive(Clone)]
 struct AudioMixerSource {
     ssrc: i32,
@@ -424,14 +844,94 @@ impl libwebrtc::native::audio_mixer::AudioMixerSource for AudioMixerSource {
     }
 }
 
+/// A mixer source that emits the loopback probe once, in a single 10ms frame,
+/// recording when the mixer pulled it.
+struct LoopbackProbeSource {
+    ssrc: i32,
+    played_at: Arc<Mutex<Option<Instant>>>,
+}
+
+impl libwebrtc::native::audio_mixer::AudioMixerSource for LoopbackProbeSource {
+    fn ssrc(&self) -> i32 {
+        self.ssrc
+    }
+
+    fn preferred_sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn get_audio_frame_with_info<'a>(&self, target_sample_rate: u32) -> Option<AudioFrame> {
+        assert_eq!(SAMPLE_RATE, target_sample_rate);
+        let mut played_at = self.played_at.lock();
+        if played_at.is_some() {
+            return None;
+        }
+        *played_at = Some(Instant::now());
+
+        let data = loopback_probe::probe()
+            .into_iter()
+            .chain(std::iter::repeat(0))
+            .take((SAMPLE_RATE / 100) as usize)
+            .flat_map(|sample| std::iter::repeat_n(sample, NUM_CHANNELS as usize))
+            .collect();
+        Some(AudioFrame {
+            data: Cow::Owned(data),
+            sample_rate: SAMPLE_RATE,
+            num_channels: NUM_CHANNELS,
+            samples_per_channel: SAMPLE_RATE / 100,
+        })
+    }
+}
+
+/// A video frame along with its size and timestamp, for measuring latency or laying
+/// out a view without inspecting the buffer.
+#[derive(Clone)]
+pub struct TimedVideoFrame {
+    pub frame: RemoteVideoFrame,
+    pub width: u32,
+    pub height: u32,
+    /// When WebRTC stamped the frame, on its own monotonic clock: the capture time
+    /// for local frames, and the time it's due to be rendered for remote ones. The
+    /// sender's capture time isn't carried over the network.
+    pub timestamp: Duration,
+    /// Whether to draw the frame flipped horizontally, i.e. a local camera's self
+    /// view while [`crate::LocalVideoTrack::set_self_view_mirrored`] is on. The
+    /// frame itself is never flipped.
+    pub mirrored: bool,
+}
+
 pub fn play_remote_video_track(
     track: &crate::RemoteVideoTrack,
 ) -> impl Stream<Item = RemoteVideoFrame> + use<> {
+    play_timed_remote_video_track(track).map(|timed| timed.frame)
+}
+
+pub(crate) fn play_timed_remote_video_track(
+    track: &crate::RemoteVideoTrack,
+) -> impl Stream<Item = TimedVideoFrame> + use<> {
+    play_video_track(track.0.rtc_track())
+}
+
+/// Frames from a local track, tapped before they're encoded. The self view flag is
+/// read for every frame, so toggling it takes effect immediately.
+pub(crate) fn play_local_video_track(
+    track: &crate::LocalVideoTrack,
+) -> impl Stream<Item = TimedVideoFrame> + use<> {
+    let mirrored = track.1.clone();
+    play_video_track(track.0.rtc_track()).map(move |mut frame| {
+        frame.mirrored = mirrored.load(atomic::Ordering::Relaxed);
+        frame
+    })
+}
+
+fn play_video_track(
+    rtc_track: livekit::webrtc::video_track::RtcVideoTrack,
+) -> impl Stream<Item = TimedVideoFrame> + use<> {
     #[cfg(target_os = "macos")]
     {
         let mut pool = None;
         let most_recent_frame_size = (0, 0);
-        NativeVideoStream::new(track.0.rtc_track()).filter_map(move |frame| {
+        NativeVideoStream::new(rtc_track).filter_map(move |frame| {
             if pool == None
                 || most_recent_frame_size != (frame.buffer.width(), frame.buffer.height())
             {
@@ -446,14 +946,31 @@ pub fn play_remote_video_track(
                     return None;
                 }
 
-                video_frame_buffer_from_webrtc(pool?, frame.buffer)
+                let (width, height) = (frame.buffer.width(), frame.buffer.height());
+                let timestamp = Duration::from_micros(frame.timestamp_us.max(0) as u64);
+                Some(TimedVideoFrame {
+                    frame: video_frame_buffer_from_webrtc(pool?, frame.buffer)?,
+                    width,
+                    height,
+                    timestamp,
+                    mirrored: false,
+                })
             }
         })
     }
     #[cfg(not(target_os = "macos"))]
     {
-        NativeVideoStream::new(track.0.rtc_track())
-            .filter_map(|frame| async move { video_frame_buffer_from_webrtc(frame.buffer) })
+        NativeVideoStream::new(rtc_track).filter_map(|frame| async move {
+            let (width, height) = (frame.buffer.width(), frame.buffer.height());
+            let timestamp = Duration::from_micros(frame.timestamp_us.max(0) as u64);
+            Some(TimedVideoFrame {
+                frame: video_frame_buffer_from_webrtc(frame.buffer)?,
+                width,
+                height,
+                timestamp,
+                mirrored: false,
+            })
+        })
     }
 }
 
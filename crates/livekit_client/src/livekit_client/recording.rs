@@ -0,0 +1,268 @@
+use std::path::PathBuf;
+
+use anyhow::{Result, anyhow};
+use futures::{StreamExt as _, channel::oneshot};
+use gpui::{BackgroundExecutor, Task};
+
+use super::play_remote_video_track;
+
+/// A local recording of a single remote video track, started with
+/// [`super::RemoteVideoTrack::start_recording`]. Dropping the handle without
+/// calling [`RecordingHandle::stop`] discards the recording.
+pub struct RecordingHandle {
+    stop_tx: oneshot::Sender<()>,
+    task: Task<Result<()>>,
+}
+
+impl RecordingHandle {
+    /// Stops recording and finalizes the file. Any error encountered while
+    /// recording, e.g. running out of disk space, is returned here.
+    pub async fn stop(self) -> Result<()> {
+        self.stop_tx.send(()).ok();
+        self.task.await
+    }
+}
+
+pub(crate) fn start_recording(
+    track: &super::RemoteVideoTrack,
+    path: PathBuf,
+    executor: &BackgroundExecutor,
+) -> RecordingHandle {
+    let (stop_tx, stop_rx) = oneshot::channel();
+    let frames = play_remote_video_track(track).map(Some);
+    let stopped = stop_rx.into_stream().map(|_| None);
+    let task = executor.spawn(async move {
+        let mut frames = std::pin::pin!(futures::stream::select(frames, stopped));
+        let mut writer = None;
+        while let Some(Some(frame)) = frames.next().await {
+            record_frame(&mut writer, &path, frame)?;
+        }
+        match writer {
+            Some(writer) => finish(writer),
+            None => Err(anyhow!("no video was received while recording")),
+        }
+    });
+    RecordingHandle { stop_tx, task }
+}
+
+#[cfg(target_os = "macos")]
+fn record_frame(
+    writer: &mut Option<macos::Mp4Writer>,
+    path: &std::path::Path,
+    frame: super::RemoteVideoFrame,
+) -> Result<()> {
+    // The file is created once the first frame arrives, since the encoder needs
+    // to know the track's dimensions.
+    match writer {
+        Some(writer) => writer.append(&frame),
+        None => {
+            let mut new_writer =
+                macos::Mp4Writer::new(path, frame.get_width(), frame.get_height())?;
+            new_writer.append(&frame)?;
+            *writer = Some(new_writer);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn finish(writer: macos::Mp4Writer) -> Result<()> {
+    writer.finish()
+}
+
+#[cfg(not(target_os = "macos"))]
+fn record_frame(
+    _writer: &mut Option<()>,
+    _path: &std::path::Path,
+    _frame: super::RemoteVideoFrame,
+) -> Result<()> {
+    Err(anyhow!("recording is only supported on macOS"))
+}
+
+#[cfg(not(target_os = "macos"))]
+fn finish(_writer: ()) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use std::path::Path;
+    use std::time::Instant;
+
+    use anyhow::{Context as _, Result, anyhow};
+    use core_foundation::{
+        base::TCFType,
+        dictionary::CFDictionary,
+        number::CFNumber,
+        string::{CFString, CFStringRef},
+    };
+    use core_video::pixel_buffer::CVPixelBuffer;
+    use objc::runtime::{BOOL, NO, Object, YES};
+    use objc::{class, msg_send, sel, sel_impl};
+
+    #[allow(non_camel_case_types)]
+    type id = *mut Object;
+
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    struct CMTime {
+        value: i64,
+        timescale: i32,
+        flags: u32,
+        epoch: i64,
+    }
+
+    const CM_TIME_FLAGS_VALID: u32 = 1;
+    const AV_ASSET_WRITER_STATUS_WRITING: isize = 1;
+    const MICROS_PER_SECOND: i32 = 1_000_000;
+
+    #[link(name = "AVFoundation", kind = "framework")]
+    unsafe extern "C" {
+        static AVFileTypeMPEG4: CFStringRef;
+        static AVMediaTypeVideo: CFStringRef;
+        static AVVideoCodecKey: CFStringRef;
+        static AVVideoCodecTypeH264: CFStringRef;
+        static AVVideoWidthKey: CFStringRef;
+        static AVVideoHeightKey: CFStringRef;
+    }
+
+    /// Encodes pixel buffers to H.264 in an MP4 container using `AVAssetWriter`.
+    pub(super) struct Mp4Writer {
+        writer: id,
+        input: id,
+        adaptor: id,
+        started_at: Option<Instant>,
+    }
+
+    // The writer is only ever used from the recording task, one call at a time.
+    unsafe impl Send for Mp4Writer {}
+
+    impl Mp4Writer {
+        pub(super) fn new(path: &Path, width: usize, height: usize) -> Result<Self> {
+            let path = path.to_str().context("recording path is not valid UTF-8")?;
+            unsafe {
+                let path = CFString::new(path);
+                let url: id = msg_send![class!(NSURL), fileURLWithPath: path.as_concrete_TypeRef()];
+                let mut error: id = std::ptr::null_mut();
+                let writer: id = msg_send![class!(AVAssetWriter), alloc];
+                let writer: id =
+                    msg_send![writer, initWithURL: url fileType: AVFileTypeMPEG4 error: &mut error];
+                if writer.is_null() {
+                    return Err(anyhow!("failed to create recording: {}", describe(error)));
+                }
+
+                let settings = CFDictionary::from_CFType_pairs(&[
+                    (
+                        CFString::wrap_under_get_rule(AVVideoCodecKey),
+                        CFString::wrap_under_get_rule(AVVideoCodecTypeH264).as_CFType(),
+                    ),
+                    (
+                        CFString::wrap_under_get_rule(AVVideoWidthKey),
+                        CFNumber::from(width as i64).as_CFType(),
+                    ),
+                    (
+                        CFString::wrap_under_get_rule(AVVideoHeightKey),
+                        CFNumber::from(height as i64).as_CFType(),
+                    ),
+                ]);
+                let input: id = msg_send![class!(AVAssetWriterInput), alloc];
+                let input: id = msg_send![input, initWithMediaType: AVMediaTypeVideo outputSettings: settings.as_concrete_TypeRef()];
+                let _: () = msg_send![input, setExpectsMediaDataInRealTime: YES];
+                let adaptor: id = msg_send![class!(AVAssetWriterInputPixelBufferAdaptor), alloc];
+                let adaptor: id = msg_send![adaptor, initWithAssetWriterInput: input sourcePixelBufferAttributes: std::ptr::null_mut::<Object>()];
+                let this = Self {
+                    writer,
+                    input,
+                    adaptor,
+                    started_at: None,
+                };
+
+                let can_add_input: BOOL = msg_send![writer, canAddInput: input];
+                if can_add_input == NO {
+                    return Err(anyhow!("failed to add video input to recording"));
+                }
+                let _: () = msg_send![writer, addInput: input];
+                let started: BOOL = msg_send![writer, startWriting];
+                if started == NO {
+                    return Err(this.error("failed to start recording"));
+                }
+                Ok(this)
+            }
+        }
+
+        pub(super) fn append(&mut self, frame: &CVPixelBuffer) -> Result<()> {
+            let now = Instant::now();
+            unsafe {
+                let started_at = match self.started_at {
+                    Some(started_at) => started_at,
+                    None => {
+                        let _: () = msg_send![self.writer, startSessionAtSourceTime: cm_time(0)];
+                        *self.started_at.insert(now)
+                    }
+                };
+                // Frames that arrive while the encoder is busy are dropped rather than
+                // buffered, as the track is live and will keep producing them.
+                let ready: BOOL = msg_send![self.input, isReadyForMoreMediaData];
+                if ready == NO {
+                    return Ok(());
+                }
+                let time = cm_time(now.duration_since(started_at).as_micros() as i64);
+                let appended: BOOL = msg_send![self.adaptor, appendPixelBuffer: frame.as_concrete_TypeRef() withPresentationTime: time];
+                if appended == NO {
+                    return Err(self.error("failed to write to recording"));
+                }
+            }
+            Ok(())
+        }
+
+        pub(super) fn finish(self) -> Result<()> {
+            unsafe {
+                let _: () = msg_send![self.input, markAsFinished];
+                #[allow(deprecated)]
+                let finished: BOOL = msg_send![self.writer, finishWriting];
+                if finished == NO {
+                    return Err(self.error("failed to finalize recording"));
+                }
+            }
+            Ok(())
+        }
+
+        unsafe fn error(&self, message: &str) -> anyhow::Error {
+            let error: id = unsafe { msg_send![self.writer, error] };
+            anyhow!("{message}: {}", unsafe { describe(error) })
+        }
+    }
+
+    impl Drop for Mp4Writer {
+        fn drop(&mut self) {
+            unsafe {
+                // A recording that's still in progress wasn't stopped cleanly, so
+                // remove the incomplete file.
+                let status: isize = msg_send![self.writer, status];
+                if status == AV_ASSET_WRITER_STATUS_WRITING {
+                    let _: () = msg_send![self.writer, cancelWriting];
+                }
+                let _: () = msg_send![self.adaptor, release];
+                let _: () = msg_send![self.input, release];
+                let _: () = msg_send![self.writer, release];
+            }
+        }
+    }
+
+    fn cm_time(micros: i64) -> CMTime {
+        CMTime {
+            value: micros,
+            timescale: MICROS_PER_SECOND,
+            flags: CM_TIME_FLAGS_VALID,
+            epoch: 0,
+        }
+    }
+
+    unsafe fn describe(error: id) -> String {
+        if error.is_null() {
+            return "unknown error".into();
+        }
+        let description: id = unsafe { msg_send![error, localizedDescription] };
+        unsafe { CFString::wrap_under_get_rule(description as CFStringRef) }.to_string()
+    }
+}
@@ -0,0 +1,752 @@
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+
+use collections::{BTreeMap, BTreeSet};
+use futures::channel::mpsc;
+use futures::future::{self, BoxFuture};
+use gpui::{AsyncApp, Task};
+use gpui_tokio::Tokio;
+use livekit::webrtc::stats::RtcStats;
+use parking_lot::Mutex;
+
+use super::RoomState;
+use crate::track_totals::TrackTotals;
+use crate::{
+    BandwidthEstimate, CandidatePair, DataChannelStats, EncoderRestart, IceCandidate, LayerStats,
+    RoomEvent, RtpParameters, SubscriptionStats, TrackPriority, TrackSid, TrackStats,
+};
+
+/// Roughly -60 dBFS. Even a quiet room is well above this, so anything below it
+/// usually means the publisher's microphone is producing digital silence.
+const SILENCE_LEVEL: f64 = 0.001;
+
+/// The stats of every track a room has published or subscribed to, keyed by track
+/// sid, as of the last poll.
+type PolledStats = BTreeMap<String, Vec<RtcStats>>;
+
+/// The latest stats for a single room, refreshed by its poller, so that accessors
+/// never call into WebRTC's `getStats` directly. Track handles read this through
+/// the room that created them.
+#[derive(Default)]
+pub(crate) struct StatsCache {
+    tracks: Mutex<TrackStatsCache>,
+    /// Listeners registered through `LocalVideoTrack::encoder_restarts`, keyed by
+    /// track sid, which are notified as restarts are detected between polls.
+    encoder_restart_txs: Mutex<BTreeMap<String, Vec<mpsc::UnboundedSender<EncoderRestart>>>>,
+    data_channels: Mutex<DataChannelStats>,
+    bandwidth: Mutex<BandwidthEstimate>,
+    bandwidth_txs: Mutex<Vec<mpsc::UnboundedSender<BandwidthEstimate>>>,
+    selected_candidate_pair: Mutex<Option<CandidatePair>>,
+}
+
+/// Per-track stats, all of which are replaced on every poll except for the totals.
+#[derive(Default)]
+struct TrackStatsCache {
+    stats: PolledStats,
+    /// Subscribed audio tracks whose level stayed below [`SILENCE_LEVEL`] for the
+    /// whole of the last polling interval.
+    silent_tracks: BTreeSet<String>,
+    /// The video encodings each subscribed track received over the last polling
+    /// interval.
+    layer_stats: BTreeMap<String, Vec<LayerStats>>,
+    /// The average time local video frames spent being encoded and then waiting to
+    /// be sent over the last polling interval.
+    send_latencies: BTreeMap<String, Duration>,
+    /// How far apart each remote video track is from its publisher's microphone
+    /// audio as of the last poll.
+    av_sync_offsets: BTreeMap<String, Duration>,
+    /// Byte counts for every track the room has polled, kept for as long as the room
+    /// is connected, including across reconnects.
+    totals: BTreeMap<String, TrackTotals>,
+}
+
+impl TrackStatsCache {
+    fn update(
+        &mut self,
+        tracks: Vec<(TrackSid, Vec<RtcStats>)>,
+        silent_tracks: Vec<TrackSid>,
+        layer_stats: Vec<(TrackSid, Vec<LayerStats>)>,
+        send_latencies: Vec<(TrackSid, Duration)>,
+        av_sync_offsets: Vec<(TrackSid, Duration)>,
+    ) {
+        self.stats.clear();
+        for (sid, stats) in tracks {
+            self.totals
+                .entry(sid.to_string())
+                .or_default()
+                .update(summarize(&stats));
+            self.stats.insert(sid.to_string(), stats);
+        }
+        self.silent_tracks = silent_tracks
+            .into_iter()
+            .map(|sid| sid.to_string())
+            .collect();
+        self.layer_stats = layer_stats
+            .into_iter()
+            .map(|(sid, layers)| (sid.to_string(), layers))
+            .collect();
+        self.send_latencies = send_latencies
+            .into_iter()
+            .map(|(sid, latency)| (sid.to_string(), latency))
+            .collect();
+        self.av_sync_offsets = av_sync_offsets
+            .into_iter()
+            .map(|(sid, offset)| (sid.to_string(), offset))
+            .collect();
+    }
+}
+
+impl StatsCache {
+    pub(crate) fn with_track_stats<R>(
+        &self,
+        sid: &TrackSid,
+        f: impl FnOnce(&[RtcStats]) -> R,
+    ) -> Option<R> {
+        self.tracks
+            .lock()
+            .stats
+            .get(sid.as_str())
+            .map(|stats| f(stats))
+    }
+
+    pub(crate) fn track_stats(&self, sid: &TrackSid) -> Option<TrackStats> {
+        self.with_track_stats(sid, summarize)
+    }
+
+    pub(crate) fn total_track_stats(&self, sid: &TrackSid) -> TrackStats {
+        self.tracks
+            .lock()
+            .totals
+            .get(sid.as_str())
+            .map_or_else(TrackStats::default, TrackTotals::total)
+    }
+
+    pub(crate) fn encoder_restarts(
+        &self,
+        sid: &TrackSid,
+    ) -> mpsc::UnboundedReceiver<EncoderRestart> {
+        let (tx, rx) = mpsc::unbounded();
+        self.encoder_restart_txs
+            .lock()
+            .entry(sid.to_string())
+            .or_default()
+            .push(tx);
+        rx
+    }
+
+    /// Whether the track's video is encoded or decoded in hardware, per WebRTC's
+    /// `powerEfficientEncoder`/`powerEfficientDecoder` stats. A local track counts as
+    /// hardware accelerated if any of its layers is.
+    pub(crate) fn is_hardware_accelerated(&self, sid: &TrackSid) -> Option<bool> {
+        self.with_track_stats(sid, |stats| {
+            stats
+                .iter()
+                .filter_map(|stat| match stat {
+                    RtcStats::OutboundRtp(outbound) if outbound.stream.kind == "video" => {
+                        Some(outbound.outbound.power_efficient_encoder)
+                    }
+                    RtcStats::InboundRtp(inbound) if inbound.stream.kind == "video" => {
+                        Some(inbound.inbound.power_efficient_decoder)
+                    }
+                    _ => None,
+                })
+                .reduce(|any, accelerated| any || accelerated)
+        })?
+    }
+
+    pub(crate) fn is_silent(&self, sid: &TrackSid) -> bool {
+        self.tracks.lock().silent_tracks.contains(sid.as_str())
+    }
+
+    pub(crate) fn capture_to_encode_latency(&self, sid: &TrackSid) -> Option<Duration> {
+        self.tracks.lock().send_latencies.get(sid.as_str()).copied()
+    }
+
+    pub(crate) fn av_sync_offset(&self, sid: &TrackSid) -> Option<Duration> {
+        self.tracks
+            .lock()
+            .av_sync_offsets
+            .get(sid.as_str())
+            .copied()
+    }
+
+    pub(crate) fn rtp_parameters(&self, sid: &TrackSid) -> Option<RtpParameters> {
+        self.with_track_stats(sid, |stats| {
+            let codec_id = stats.iter().find_map(|stat| match stat {
+                RtcStats::InboundRtp(inbound) => Some(&inbound.stream.codec_id),
+                _ => None,
+            })?;
+            stats.iter().find_map(|stat| match stat {
+                RtcStats::Codec(codec) if codec.rtc.id == *codec_id => Some(RtpParameters {
+                    mime_type: codec.codec.mime_type.clone(),
+                    clock_rate: codec.codec.clock_rate,
+                    payload_type: codec.codec.payload_type,
+                }),
+                _ => None,
+            })
+        })?
+    }
+
+    pub(crate) fn layer_stats(&self, sid: &TrackSid) -> Vec<LayerStats> {
+        self.tracks
+            .lock()
+            .layer_stats
+            .get(sid.as_str())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn subscription_stats(&self, sid: &TrackSid) -> Option<SubscriptionStats> {
+        let bitrate_bps = self
+            .layer_stats(sid)
+            .iter()
+            .map(|layer| layer.bitrate_bps)
+            .sum();
+        self.with_track_stats(sid, |stats| {
+            stats.iter().find_map(|stat| match stat {
+                RtcStats::InboundRtp(inbound) if inbound.stream.kind == "video" => {
+                    Some(SubscriptionStats {
+                        bitrate_bps,
+                        packets_lost: inbound.received.packets_lost.max(0) as u64,
+                        jitter: Duration::from_secs_f64(inbound.received.jitter.max(0.)),
+                        frames_per_second: inbound.inbound.frames_per_second,
+                        width: inbound.inbound.frame_width,
+                        height: inbound.inbound.frame_height,
+                    })
+                }
+                _ => None,
+            })
+        })?
+    }
+
+    pub(crate) fn data_channel_stats(&self) -> DataChannelStats {
+        *self.data_channels.lock()
+    }
+
+    /// Subscribes to bandwidth estimates, starting with the current one.
+    pub(crate) fn bandwidth_updates(&self) -> mpsc::UnboundedReceiver<BandwidthEstimate> {
+        let (tx, rx) = mpsc::unbounded();
+        tx.unbounded_send(*self.bandwidth.lock()).ok();
+        self.bandwidth_txs.lock().push(tx);
+        rx
+    }
+
+    pub(crate) fn selected_candidate_pair(&self) -> Option<CandidatePair> {
+        self.selected_candidate_pair.lock().clone()
+    }
+
+    fn update_bandwidth(&self, estimate: BandwidthEstimate) {
+        let mut bandwidth = self.bandwidth.lock();
+        if *bandwidth == estimate {
+            return;
+        }
+        *bandwidth = estimate;
+        let mut bandwidth_txs = self.bandwidth_txs.lock();
+        bandwidth_txs.retain(|tx| tx.unbounded_send(estimate).is_ok());
+    }
+
+    /// Compares freshly polled stats against the previous poll, notifying listeners
+    /// of any local video track whose encoded resolution or encoder changed.
+    fn notify_encoder_restarts(
+        &self,
+        previous: &PolledStats,
+        tracks: &[(TrackSid, Vec<RtcStats>)],
+    ) {
+        let mut all_txs = self.encoder_restart_txs.lock();
+        all_txs.retain(|_, txs| {
+            txs.retain(|tx| !tx.is_closed());
+            !txs.is_empty()
+        });
+        for (sid, stats) in tracks {
+            let Some(txs) = all_txs.get(sid.as_str()) else {
+                continue;
+            };
+            let Some((resolution, encoder)) = encoder_state(stats) else {
+                continue;
+            };
+            let Some((previous_resolution, previous_encoder)) = previous
+                .get(sid.as_str())
+                .and_then(|previous| encoder_state(previous))
+            else {
+                continue;
+            };
+            if resolution != previous_resolution || encoder != previous_encoder {
+                let restart = EncoderRestart {
+                    previous_resolution,
+                    resolution,
+                    previous_encoder,
+                    encoder,
+                };
+                for tx in txs {
+                    tx.unbounded_send(restart.clone()).ok();
+                }
+            }
+        }
+    }
+}
+
+fn summarize(stats: &[RtcStats]) -> TrackStats {
+    let mut summary = TrackStats::default();
+    for stat in stats {
+        match stat {
+            RtcStats::OutboundRtp(outbound) => {
+                summary.bytes_sent += outbound.sent.bytes_sent;
+            }
+            RtcStats::InboundRtp(inbound) => {
+                summary.bytes_received += inbound.inbound.bytes_received;
+            }
+            _ => {}
+        }
+    }
+    summary
+}
+
+/// The fraction of received frames that have to be dropped between two polls for
+/// a track to be considered overloaded.
+const DECODE_OVERLOAD_DROP_RATIO: u64 = 10;
+
+pub(crate) fn poll_stats(
+    room: Weak<livekit::Room>,
+    state: Arc<RoomState>,
+    interval: Duration,
+    downgrade_on_decode_overload: bool,
+    events: mpsc::UnboundedSender<RoomEvent>,
+    cx: &mut AsyncApp,
+) -> Task<()> {
+    cx.spawn(async move |cx| {
+        let cache = &state.stats;
+        let mut overloaded = false;
+        let mut last_polled_at = None::<Instant>;
+        loop {
+            let Some(room) = room.upgrade() else {
+                break;
+            };
+            let requests = track_stats_requests(&room);
+            let session_stats = {
+                let room = room.clone();
+                async move { room.get_stats().await }
+            };
+
+            let Ok(task) =
+                Tokio::spawn(cx, future::join(future::join_all(requests), session_stats))
+            else {
+                break;
+            };
+            if let Ok((results, session_stats)) = task.await {
+                let tracks = results
+                    .into_iter()
+                    .filter_map(|(sid, stats)| Some((sid, stats.ok()?)))
+                    .collect::<Vec<_>>();
+                let av_sync_offsets = av_sync_offsets(&room, &tracks);
+                let polled_at = Instant::now();
+                let elapsed = last_polled_at.map(|last_polled_at| polled_at - last_polled_at);
+                last_polled_at = Some(polled_at);
+                let overloaded_tracks = {
+                    let mut track_stats = cache.tracks.lock();
+                    let previous = &track_stats.stats;
+                    let overloaded_tracks = decode_overloaded_tracks(previous, &tracks);
+                    let silent_tracks = silent_tracks(previous, &tracks);
+                    let layer_stats = elapsed
+                        .map(|elapsed| layer_stats_since(previous, &tracks, elapsed))
+                        .unwrap_or_default();
+                    let send_latencies = send_latencies(previous, &tracks);
+                    cache.notify_encoder_restarts(previous, &tracks);
+                    track_stats.update(
+                        tracks,
+                        silent_tracks,
+                        layer_stats,
+                        send_latencies,
+                        av_sync_offsets,
+                    );
+                    overloaded_tracks
+                };
+                if overloaded_tracks.is_empty() {
+                    overloaded = false;
+                } else if !overloaded {
+                    overloaded = true;
+                    if downgrade_on_decode_overload {
+                        downgrade_tracks(&room, &state, &overloaded_tracks, cx);
+                    }
+                    events
+                        .unbounded_send(RoomEvent::DecodeOverloaded {
+                            track_sids: overloaded_tracks,
+                        })
+                        .ok();
+                }
+
+                if let Ok(session_stats) = session_stats {
+                    *cache.data_channels.lock() = data_channel_stats(
+                        session_stats
+                            .publisher_stats
+                            .iter()
+                            .chain(&session_stats.subscriber_stats),
+                    );
+                    *cache.selected_candidate_pair.lock() =
+                        selected_candidate_pair(&session_stats.subscriber_stats)
+                            .or_else(|| selected_candidate_pair(&session_stats.publisher_stats));
+                    cache.update_bandwidth(BandwidthEstimate {
+                        outgoing: nominated_candidate_pair(&session_stats.publisher_stats)
+                            .map(|pair| pair.available_outgoing_bitrate as u64),
+                        incoming: nominated_candidate_pair(&session_stats.subscriber_stats)
+                            .map(|pair| pair.available_incoming_bitrate as u64),
+                    });
+                }
+            }
+
+            // Don't keep the room alive while waiting for the next poll.
+            drop(room);
+            cx.background_executor().timer(interval).await;
+        }
+    })
+}
+
+/// Compares freshly polled stats against the previous poll to find video tracks
+/// that are dropping frames.
+fn decode_overloaded_tracks(
+    previous: &PolledStats,
+    tracks: &[(TrackSid, Vec<RtcStats>)],
+) -> Vec<TrackSid> {
+    tracks
+        .iter()
+        .filter_map(|(sid, stats)| {
+            let (received, dropped) = inbound_frame_counts(stats)?;
+            let (previous_received, previous_dropped) =
+                inbound_frame_counts(previous.get(sid.as_str())?)?;
+            let received = received.saturating_sub(previous_received);
+            let dropped = dropped.saturating_sub(previous_dropped);
+            (received > 0 && dropped * DECODE_OVERLOAD_DROP_RATIO > received).then(|| sid.clone())
+        })
+        .collect()
+}
+
+/// Compares freshly polled stats against the previous poll to find audio tracks
+/// whose average level over the interval was below [`SILENCE_LEVEL`]. This uses
+/// the received audio energy rather than mute state, since a broken microphone
+/// sends silence while appearing unmuted.
+fn silent_tracks(previous: &PolledStats, tracks: &[(TrackSid, Vec<RtcStats>)]) -> Vec<TrackSid> {
+    tracks
+        .iter()
+        .filter_map(|(sid, stats)| {
+            let (energy, duration) = inbound_audio_energy(stats)?;
+            let (previous_energy, previous_duration) =
+                inbound_audio_energy(previous.get(sid.as_str())?)?;
+            let duration = duration - previous_duration;
+            if duration <= 0. {
+                return None;
+            }
+            let level = ((energy - previous_energy).max(0.) / duration).sqrt();
+            (level < SILENCE_LEVEL).then(|| sid.clone())
+        })
+        .collect()
+}
+
+/// Compares freshly polled stats against the previous poll to find each video
+/// encoding received over the interval and its bitrate. Encodings are told apart
+/// by SSRC, so one that only started arriving during the interval is left out
+/// until the next poll.
+fn layer_stats_since(
+    previous: &PolledStats,
+    tracks: &[(TrackSid, Vec<RtcStats>)],
+    elapsed: Duration,
+) -> Vec<(TrackSid, Vec<LayerStats>)> {
+    if elapsed.is_zero() {
+        return Vec::new();
+    }
+    tracks
+        .iter()
+        .filter_map(|(sid, stats)| {
+            let previous = previous.get(sid.as_str());
+            let layers = stats
+                .iter()
+                .filter_map(|stat| match stat {
+                    RtcStats::InboundRtp(inbound) if inbound.stream.kind == "video" => {
+                        let previous_bytes = inbound_video_bytes(previous?, inbound.stream.ssrc)?;
+                        let bytes = inbound
+                            .inbound
+                            .bytes_received
+                            .saturating_sub(previous_bytes);
+                        Some(LayerStats {
+                            width: inbound.inbound.frame_width,
+                            height: inbound.inbound.frame_height,
+                            frames_per_second: inbound.inbound.frames_per_second,
+                            bitrate_bps: (bytes as f64 * 8. / elapsed.as_secs_f64()) as u64,
+                        })
+                    }
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            (!layers.is_empty()).then(|| (sid.clone(), layers))
+        })
+        .collect()
+}
+
+fn inbound_video_bytes(stats: &[RtcStats], ssrc: u32) -> Option<u64> {
+    stats.iter().find_map(|stat| match stat {
+        RtcStats::InboundRtp(inbound)
+            if inbound.stream.kind == "video" && inbound.stream.ssrc == ssrc =>
+        {
+            Some(inbound.inbound.bytes_received)
+        }
+        _ => None,
+    })
+}
+
+/// Compares freshly polled stats against the previous poll to find how long local
+/// video frames took on average to be encoded, plus how long their packets then
+/// waited to be sent, over the interval.
+fn send_latencies(
+    previous: &PolledStats,
+    tracks: &[(TrackSid, Vec<RtcStats>)],
+) -> Vec<(TrackSid, Duration)> {
+    tracks
+        .iter()
+        .filter_map(|(sid, stats)| {
+            let timing = outbound_video_timing(stats)?;
+            let previous = outbound_video_timing(previous.get(sid.as_str())?)?;
+            let frames = timing.frames_encoded.checked_sub(previous.frames_encoded)?;
+            let packets = timing.packets_sent.checked_sub(previous.packets_sent)?;
+            if frames == 0 || packets == 0 {
+                return None;
+            }
+            let encode_time = (timing.encode_time - previous.encode_time) / frames as f64;
+            let send_delay = (timing.send_delay - previous.send_delay) / packets as f64;
+            let latency = (encode_time + send_delay).max(0.);
+            Some((sid.clone(), Duration::from_secs_f64(latency)))
+        })
+        .collect()
+}
+
+/// Pairs each remote video track with its publisher's microphone track and compares
+/// when WebRTC estimates each will next be played out. The difference approximates
+/// how far out of sync they are.
+fn av_sync_offsets(
+    room: &livekit::Room,
+    tracks: &[(TrackSid, Vec<RtcStats>)],
+) -> Vec<(TrackSid, Duration)> {
+    let playout_timestamps = tracks
+        .iter()
+        .filter_map(|(sid, stats)| Some((sid.to_string(), estimated_playout_timestamp(stats)?)))
+        .collect::<BTreeMap<_, _>>();
+    let playout_timestamps = &playout_timestamps;
+    room.remote_participants()
+        .into_values()
+        .flat_map(|participant| {
+            let publications = participant.track_publications();
+            let audio = publications
+                .values()
+                .filter(|publication| {
+                    publication.source() == livekit::track::TrackSource::Microphone
+                })
+                .find_map(|publication| playout_timestamps.get(publication.sid().as_str()))
+                .copied();
+            publications.into_values().filter_map(move |publication| {
+                if publication.kind() != livekit::track::TrackKind::Video {
+                    return None;
+                }
+                let video = playout_timestamps.get(publication.sid().as_str())?;
+                let offset_ms = (video - audio?).abs();
+                Some((
+                    publication.sid(),
+                    Duration::from_secs_f64(offset_ms / 1000.),
+                ))
+            })
+        })
+        .collect()
+}
+
+/// The resolution and implementation of a local video track's highest encoded layer.
+fn encoder_state(stats: &[RtcStats]) -> Option<((u32, u32), String)> {
+    stats
+        .iter()
+        .filter_map(|stat| match stat {
+            RtcStats::OutboundRtp(outbound) if outbound.outbound.frame_width > 0 => Some((
+                (
+                    outbound.outbound.frame_width,
+                    outbound.outbound.frame_height,
+                ),
+                outbound.outbound.encoder_implementation.clone(),
+            )),
+            _ => None,
+        })
+        .max_by_key(|((width, height), _)| width * height)
+}
+
+struct OutboundVideoTiming {
+    frames_encoded: u64,
+    encode_time: f64,
+    packets_sent: u64,
+    send_delay: f64,
+}
+
+/// Cumulative encoder and pacer timings summed across a local video track's layers.
+fn outbound_video_timing(stats: &[RtcStats]) -> Option<OutboundVideoTiming> {
+    stats
+        .iter()
+        .filter_map(|stat| match stat {
+            RtcStats::OutboundRtp(outbound) if outbound.stream.kind == "video" => {
+                Some(OutboundVideoTiming {
+                    frames_encoded: outbound.outbound.frames_encoded as u64,
+                    encode_time: outbound.outbound.total_encode_time,
+                    packets_sent: outbound.sent.packets_sent,
+                    send_delay: outbound.outbound.total_packet_send_delay,
+                })
+            }
+            _ => None,
+        })
+        .reduce(|total, layer| OutboundVideoTiming {
+            frames_encoded: total.frames_encoded + layer.frames_encoded,
+            encode_time: total.encode_time + layer.encode_time,
+            packets_sent: total.packets_sent + layer.packets_sent,
+            send_delay: total.send_delay + layer.send_delay,
+        })
+}
+
+/// The NTP time, in milliseconds, at which WebRTC expects the track's latest
+/// received media to be played out.
+fn estimated_playout_timestamp(stats: &[RtcStats]) -> Option<f64> {
+    stats.iter().find_map(|stat| match stat {
+        RtcStats::InboundRtp(inbound) if inbound.inbound.estimated_playout_timestamp > 0. => {
+            Some(inbound.inbound.estimated_playout_timestamp)
+        }
+        _ => None,
+    })
+}
+
+fn inbound_audio_energy(stats: &[RtcStats]) -> Option<(f64, f64)> {
+    stats.iter().find_map(|stat| match stat {
+        RtcStats::InboundRtp(inbound) if inbound.stream.kind == "audio" => Some((
+            inbound.inbound.total_audio_energy,
+            inbound.inbound.total_samples_duration,
+        )),
+        _ => None,
+    })
+}
+
+fn inbound_frame_counts(stats: &[RtcStats]) -> Option<(u64, u64)> {
+    stats.iter().find_map(|stat| match stat {
+        RtcStats::InboundRtp(inbound) if inbound.stream.kind == "video" => Some((
+            inbound.inbound.frames_received as u64,
+            inbound.inbound.frames_dropped as u64,
+        )),
+        _ => None,
+    })
+}
+
+/// Requests the lowest simulcast layer for the given tracks, which is the cheapest
+/// way to relieve the decoder without unsubscribing from anything.
+fn downgrade_tracks(
+    room: &livekit::Room,
+    state: &RoomState,
+    track_sids: &[TrackSid],
+    cx: &AsyncApp,
+) {
+    let settings = state.remote_track_settings.lock();
+    let publications = room
+        .remote_participants()
+        .into_values()
+        .flat_map(|participant| participant.track_publications().into_values())
+        .filter(|publication| {
+            track_sids.contains(&publication.sid())
+                && settings.priority(&publication.sid()) < TrackPriority::High
+                && settings.pinned_quality(&publication.sid()).is_none()
+        })
+        .collect::<Vec<_>>();
+    Tokio::spawn(cx, async move {
+        for publication in publications {
+            publication.set_video_quality(livekit::track::VideoQuality::Low);
+        }
+    })
+    .map(|task| task.detach())
+    .ok();
+}
+
+fn data_channel_stats<'a>(stats: impl Iterator<Item = &'a RtcStats>) -> DataChannelStats {
+    let mut summary = DataChannelStats::default();
+    for stat in stats {
+        if let RtcStats::DataChannel(data_channel) = stat {
+            summary.bytes_sent += data_channel.dc.bytes_sent;
+            summary.bytes_received += data_channel.dc.bytes_received;
+            summary.messages_sent += data_channel.dc.messages_sent as u64;
+            summary.messages_received += data_channel.dc.messages_received as u64;
+        }
+    }
+    summary
+}
+
+/// The candidate pair a transport is actually sending over, which is where WebRTC
+/// reports its bandwidth estimates.
+fn nominated_candidate_pair(
+    stats: &[RtcStats],
+) -> Option<&livekit::webrtc::stats::dictionaries::CandidatePairStats> {
+    stats.iter().find_map(|stat| match stat {
+        RtcStats::CandidatePair(pair) if pair.candidate_pair.nominated => {
+            Some(&pair.candidate_pair)
+        }
+        _ => None,
+    })
+}
+
+pub(crate) fn round_trip_time(stats: &[RtcStats]) -> Option<Duration> {
+    let pair = nominated_candidate_pair(stats)?;
+    (pair.current_round_trip_time > 0.)
+        .then(|| Duration::from_secs_f64(pair.current_round_trip_time))
+}
+
+pub(crate) fn selected_candidate_pair(stats: &[RtcStats]) -> Option<CandidatePair> {
+    let pair = nominated_candidate_pair(stats)?;
+    let local = stats.iter().find_map(|stat| match stat {
+        RtcStats::LocalCandidate(candidate) if candidate.rtc.id == pair.local_candidate_id => {
+            Some(ice_candidate(&candidate.local_candidate))
+        }
+        _ => None,
+    })?;
+    let remote = stats.iter().find_map(|stat| match stat {
+        RtcStats::RemoteCandidate(candidate) if candidate.rtc.id == pair.remote_candidate_id => {
+            Some(ice_candidate(&candidate.remote_candidate))
+        }
+        _ => None,
+    })?;
+    Some(CandidatePair { local, remote })
+}
+
+fn ice_candidate(
+    candidate: &livekit::webrtc::stats::dictionaries::IceCandidateStats,
+) -> IceCandidate {
+    IceCandidate {
+        candidate_type: format!("{:?}", candidate.candidate_type).to_lowercase(),
+        protocol: candidate.protocol.clone(),
+        address: Some(candidate.address.clone()),
+        port: candidate.port as u16,
+    }
+}
+
+type StatsRequest = BoxFuture<'static, (TrackSid, livekit::RoomResult<Vec<RtcStats>>)>;
+
+fn track_stats_requests(room: &livekit::Room) -> Vec<StatsRequest> {
+    let mut requests = Vec::<StatsRequest>::new();
+    for (sid, publication) in room.local_participant().track_publications() {
+        match publication.track() {
+            Some(livekit::track::LocalTrack::Audio(track)) => {
+                requests.push(Box::pin(async move { (sid, track.get_stats().await) }))
+            }
+            Some(livekit::track::LocalTrack::Video(track)) => {
+                requests.push(Box::pin(async move { (sid, track.get_stats().await) }))
+            }
+            None => {}
+        }
+    }
+    for participant in room.remote_participants().into_values() {
+        for (sid, publication) in participant.track_publications() {
+            match publication.track() {
+                Some(livekit::track::RemoteTrack::Audio(track)) => {
+                    requests.push(Box::pin(async move { (sid, track.get_stats().await) }))
+                }
+                Some(livekit::track::RemoteTrack::Video(track)) => {
+                    requests.push(Box::pin(async move { (sid, track.get_stats().await) }))
+                }
+                None => {}
+            }
+        }
+    }
+    requests
+}
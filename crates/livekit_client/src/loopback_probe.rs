@@ -0,0 +1,150 @@
+use std::collections::VecDeque;
+
+/// How loud the probe is played, leaving headroom for whatever else is playing.
+const PROBE_AMPLITUDE: i16 = i16::MAX / 2;
+
+/// The number of samples each chip of the probe is held for. At 48kHz, the whole
+/// probe lasts under 10ms, so it fits in a single mixer frame.
+const CHIP_SAMPLES: usize = 15;
+
+/// How closely captured audio has to match the probe, as a normalized correlation
+/// from 0 to 1, to count as having heard it. Speakers and microphones color the
+/// probe considerably, but noise and speech correlate with it far less than this.
+const MATCH_THRESHOLD: f32 = 0.5;
+
+/// The probe played by the audio loopback test, one sample per channel: a 31-chip
+/// maximum length sequence. Unlike a tone, this can't be mistaken for background
+/// noise or for the device's own hum, and its autocorrelation has a single sharp
+/// peak, which pins down when it was heard.
+pub(crate) fn probe() -> Vec<i16> {
+    // A Fibonacci LFSR for the primitive polynomial x^5 + x^3 + 1.
+    let mut lfsr = 0b11111u8;
+    (0..31)
+        .flat_map(|_| {
+            let chip = if lfsr & 1 == 1 {
+                PROBE_AMPLITUDE
+            } else {
+                -PROBE_AMPLITUDE
+            };
+            let feedback = (lfsr ^ (lfsr >> 2)) & 1;
+            lfsr = (lfsr >> 1) | (feedback << 4);
+            std::iter::repeat_n(chip, CHIP_SAMPLES)
+        })
+        .collect()
+}
+
+/// Listens for the [`probe`] in captured audio by correlating the most recent
+/// samples against it.
+pub(crate) struct ProbeDetector {
+    probe: Vec<f32>,
+    probe_norm: f32,
+    window: VecDeque<f32>,
+    /// The closest match so far. The probe ended where the match was closest, which
+    /// is known once the match starts getting worse again.
+    best_match: Option<f32>,
+}
+
+impl ProbeDetector {
+    pub(crate) fn new() -> Self {
+        let probe = probe()
+            .into_iter()
+            .map(|sample| sample as f32)
+            .collect::<Vec<_>>();
+        let probe_norm = probe
+            .iter()
+            .map(|sample| sample * sample)
+            .sum::<f32>()
+            .sqrt();
+        Self {
+            window: VecDeque::with_capacity(probe.len()),
+            probe,
+            probe_norm,
+            best_match: None,
+        }
+    }
+
+    /// Adds a frame of interleaved samples. Once the probe has been heard, returns
+    /// how many samples per channel have been captured since it started, so that
+    /// the time it took to arrive can be worked out.
+    pub(crate) fn push(&mut self, samples: &[i16], num_channels: usize) -> Option<usize> {
+        let frame_len = samples.len() / num_channels;
+        for (index, channels) in samples.chunks_exact(num_channels).enumerate() {
+            let sample =
+                channels.iter().map(|sample| *sample as f32).sum::<f32>() / num_channels as f32;
+            if self.window.len() == self.probe.len() {
+                self.window.pop_front();
+            }
+            self.window.push_back(sample);
+            if self.window.len() < self.probe.len() {
+                continue;
+            }
+
+            let correlation = self.correlation();
+            match self.best_match {
+                // The probe ended on the previous sample.
+                Some(best) if correlation <= best => {
+                    return Some(self.probe.len() + frame_len - index);
+                }
+                _ if correlation > MATCH_THRESHOLD => self.best_match = Some(correlation),
+                _ => {}
+            }
+        }
+        None
+    }
+
+    fn correlation(&self) -> f32 {
+        let (dot, energy) = self
+            .window
+            .iter()
+            .zip(&self.probe)
+            .fold((0., 0.), |(dot, energy), (sample, chip)| {
+                (dot + sample * chip, energy + sample * sample)
+            });
+        if energy == 0. {
+            return 0.;
+        }
+        // Some devices invert the signal, which doesn't make it any less of a match.
+        dot.abs() / (energy.sqrt() * self.probe_norm)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stereo(mono: &[i16]) -> Vec<i16> {
+        mono.iter().flat_map(|sample| [*sample, *sample]).collect()
+    }
+
+    #[test]
+    fn test_detecting_delayed_probe() {
+        let mut detector = ProbeDetector::new();
+        let mut captured = vec![0; 200];
+        captured.extend(probe().iter().map(|sample| -sample / 4));
+        captured.extend([0; 300]);
+
+        assert_eq!(detector.push(&stereo(&captured[..400]), 2), None);
+        // The probe ends partway through the second frame, which carries on for
+        // another 300 samples.
+        assert_eq!(
+            detector.push(&stereo(&captured[400..]), 2),
+            Some(probe().len() + 300)
+        );
+    }
+
+    #[test]
+    fn test_ignoring_loud_tone() {
+        let mut detector = ProbeDetector::new();
+        // A 1kHz square wave at 48kHz, as loud as the probe itself.
+        let tone = (0..4800)
+            .map(|index| {
+                if (index / 24) % 2 == 0 {
+                    i16::MAX / 2
+                } else {
+                    -i16::MAX / 2
+                }
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(detector.push(&stereo(&tone), 2), None);
+    }
+}
@@ -15,22 +15,92 @@ pub type LocalTrackPublication = publication::LocalTrackPublication;
 pub type LocalParticipant = participant::LocalParticipant;
 
 pub type Room = test::Room;
-pub use test::{ConnectionState, ParticipantIdentity, TrackSid};
+pub use test::{ConnectionState, ParticipantIdentity, ParticipantSid, TrackSid};
 
 pub struct AudioStream {}
 
+pub struct DevicePreview {}
+
+pub async fn test_connection(
+    url: String,
+    token: String,
+    _timeout: std::time::Duration,
+    _cx: &mut gpui::AsyncApp,
+) -> anyhow::Result<crate::ConnectionTest> {
+    test::TestServer::validate_connection(&url, &token)?;
+    Ok(crate::ConnectionTest {
+        connect_time: std::time::Duration::ZERO,
+        round_trip_time: None,
+        candidate_pair: None,
+    })
+}
+
+pub fn ensure_native_loaded() -> Result<(), crate::NativeFrameworkUnavailable> {
+    Ok(())
+}
+
+pub struct RecordingHandle {}
+
+impl RecordingHandle {
+    pub async fn stop(self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+impl DevicePreview {
+    pub fn microphone(
+        _device_name: Option<String>,
+        _cx: &gpui::App,
+    ) -> (Self, futures::channel::mpsc::UnboundedReceiver<f32>) {
+        let (_, levels) = futures::channel::mpsc::unbounded();
+        (Self {}, levels)
+    }
+}
+
 #[cfg(not(target_os = "macos"))]
 pub type RemoteVideoFrame = std::sync::Arc<gpui::RenderImage>;
 
 #[cfg(target_os = "macos")]
 #[derive(Clone)]
-pub(crate) struct RemoteVideoFrame {}
+pub struct RemoteVideoFrame {}
 #[cfg(target_os = "macos")]
 impl Into<gpui::SurfaceSource> for RemoteVideoFrame {
     fn into(self) -> gpui::SurfaceSource {
         unimplemented!()
     }
 }
+/// A blank frame, for delivering to renderers in tests.
+#[cfg(target_os = "macos")]
+pub(crate) fn test_video_frame() -> RemoteVideoFrame {
+    RemoteVideoFrame {}
+}
+
+/// A blank frame, for delivering to renderers in tests.
+#[cfg(not(target_os = "macos"))]
+pub(crate) fn test_video_frame() -> RemoteVideoFrame {
+    std::sync::Arc::new(gpui::RenderImage::new(smallvec::SmallVec::from_elem(
+        image::Frame::new(image::RgbaImage::new(1, 1)),
+        1,
+    )))
+}
+
+pub async fn screen_capture_thumbnail(
+    _source: &dyn gpui::ScreenCaptureSource,
+    _max_size: (u32, u32),
+    _cx: &gpui::AsyncApp,
+) -> anyhow::Result<RemoteVideoFrame> {
+    anyhow::bail!("screen capture thumbnails are not supported in tests")
+}
+
+#[derive(Clone)]
+pub struct TimedVideoFrame {
+    pub frame: RemoteVideoFrame,
+    pub width: u32,
+    pub height: u32,
+    pub timestamp: std::time::Duration,
+    pub mirrored: bool,
+}
+
 pub(crate) fn play_remote_video_track(
     _track: &crate::RemoteVideoTrack,
 ) -> impl futures::Stream<Item = RemoteVideoFrame> + use<> {
@@ -1,6 +1,7 @@
 use crate::{
-    AudioStream, LocalAudioTrack, LocalTrackPublication, LocalVideoTrack, Participant,
-    ParticipantIdentity, RemoteTrack, RemoteTrackPublication, TrackSid,
+    AudioCaptureOptions, AudioStream, LocalAudioTrack, LocalTrackPublication, LocalVideoTrack,
+    Participant, ParticipantIdentity, ParticipantPermissions, ParticipantSid, RecordingConsent,
+    RemoteTrack, RemoteTrackPublication, ScreenShareOptions, TrackSid,
     test::{Room, WeakRoom},
 };
 use anyhow::Result;
@@ -29,6 +30,24 @@ impl Participant {
 }
 
 impl LocalParticipant {
+    pub fn identity(&self) -> ParticipantIdentity {
+        self.identity.clone()
+    }
+
+    pub fn sid(&self) -> Option<ParticipantSid> {
+        Some(ParticipantSid(format!("PA_{}", self.identity.0)))
+    }
+
+    pub fn speaking_time(&self) -> std::time::Duration {
+        std::time::Duration::ZERO
+    }
+
+    pub fn recording_consent(&self) -> RecordingConsent {
+        self.room
+            .test_server()
+            .recording_consent(&self.room.token(), &self.identity)
+    }
+
     pub async fn unpublish_track(&self, track: TrackSid, _cx: &AsyncApp) -> Result<()> {
         self.room
             .test_server()
@@ -38,12 +57,13 @@ impl LocalParticipant {
 
     pub(crate) async fn publish_microphone_track(
         &self,
+        options: AudioCaptureOptions,
         _cx: &AsyncApp,
     ) -> Result<(LocalTrackPublication, AudioStream)> {
         let this = self.clone();
         let server = this.room.test_server();
         let sid = server
-            .publish_audio_track(this.room.token(), &LocalAudioTrack {})
+            .publish_audio_track(this.room.token(), &LocalAudioTrack {}, options.start_muted)
             .await?;
 
         Ok((
@@ -56,14 +76,24 @@ impl LocalParticipant {
     }
 
     pub async fn publish_screenshare_track(
+        &self,
+        source: &dyn ScreenCaptureSource,
+        cx: &mut AsyncApp,
+    ) -> Result<(LocalTrackPublication, Box<dyn ScreenCaptureStream>)> {
+        self.publish_screenshare_track_with_options(source, ScreenShareOptions::default(), cx)
+            .await
+    }
+
+    pub async fn publish_screenshare_track_with_options(
         &self,
         _source: &dyn ScreenCaptureSource,
+        options: ScreenShareOptions,
         _cx: &mut AsyncApp,
     ) -> Result<(LocalTrackPublication, Box<dyn ScreenCaptureStream>)> {
         let this = self.clone();
         let server = this.room.test_server();
         let sid = server
-            .publish_video_track(this.room.token(), LocalVideoTrack {})
+            .publish_video_track(this.room.token(), LocalVideoTrack::default(), options.video)
             .await?;
         Ok((
             LocalTrackPublication {
@@ -76,6 +106,10 @@ impl LocalParticipant {
 }
 
 impl RemoteParticipant {
+    pub fn speaking_time(&self) -> std::time::Duration {
+        std::time::Duration::ZERO
+    }
+
     pub fn track_publications(&self) -> HashMap<TrackSid, RemoteTrackPublication> {
         if let Some(room) = self.room.upgrade() {
             let server = room.test_server();
@@ -118,6 +152,32 @@ impl RemoteParticipant {
     pub fn identity(&self) -> ParticipantIdentity {
         self.identity.clone()
     }
+
+    pub fn recording_consent(&self) -> RecordingConsent {
+        if let Some(room) = self.room.upgrade() {
+            room.test_server()
+                .recording_consent(&room.token(), &self.identity)
+        } else {
+            RecordingConsent::Pending
+        }
+    }
+
+    pub fn sid(&self) -> ParticipantSid {
+        ParticipantSid(format!("PA_{}", self.identity.0))
+    }
+
+    pub fn has_video_capability(&self) -> bool {
+        self.permissions().can_publish_video()
+    }
+
+    pub fn permissions(&self) -> ParticipantPermissions {
+        if let Some(room) = self.room.upgrade() {
+            room.test_server()
+                .participant_permissions(&room.token(), &self.identity)
+        } else {
+            ParticipantPermissions::default()
+        }
+    }
 }
 
 struct TestScreenCaptureStream;
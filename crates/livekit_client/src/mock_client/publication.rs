@@ -1,6 +1,6 @@
 use gpui::App;
 
-use crate::{RemoteTrack, TrackSid, test::WeakRoom};
+use crate::{RemoteTrack, TrackSid, VideoQuality, test::WeakRoom};
 
 #[derive(Clone, Debug)]
 pub struct LocalTrackPublication {
@@ -60,6 +60,37 @@ impl RemoteTrackPublication {
         matches!(self.track, RemoteTrack::Audio(_))
     }
 
+    pub fn set_subscribed(&self, subscribed: bool, cx: &App) {
+        let Some(room) = self.room.upgrade() else {
+            return;
+        };
+        let server = room.test_server();
+        let token = room.token();
+        let sid = self.sid.clone();
+        cx.background_executor()
+            .spawn(async move {
+                server
+                    .set_track_subscribed(token, sid, subscribed)
+                    .await
+                    .ok();
+            })
+            .detach();
+    }
+
+    pub fn is_subscribed(&self) -> bool {
+        self.room.upgrade().is_some_and(|room| {
+            room.test_server()
+                .is_track_subscribed(&room.token(), &self.sid)
+        })
+    }
+
+    pub fn mime_type(&self) -> String {
+        match &self.track {
+            RemoteTrack::Audio(_) => "audio/opus".to_string(),
+            RemoteTrack::Video(track) => track.server_track.codec.mime_type().to_string(),
+        }
+    }
+
     pub fn is_muted(&self) -> bool {
         if let Some(room) = self.room.upgrade() {
             room.test_server()
@@ -88,6 +119,29 @@ impl RemoteTrackPublication {
             }
         }
     }
+
+    pub fn pin_quality(&self, quality: VideoQuality, _cx: &App) {
+        if let Some(room) = self.room.upgrade() {
+            room.0
+                .lock()
+                .pinned_video_qualities
+                .insert(self.sid.clone(), quality);
+        }
+    }
+
+    pub fn set_preferred_quality(&self, _quality: VideoQuality, _cx: &App) {}
+
+    pub fn unpin_quality(&self, _cx: &App) {
+        if let Some(room) = self.room.upgrade() {
+            room.0.lock().pinned_video_qualities.remove(&self.sid);
+        }
+    }
+
+    pub fn pinned_quality(&self) -> Option<VideoQuality> {
+        self.room
+            .upgrade()
+            .and_then(|room| room.0.lock().pinned_video_qualities.get(&self.sid).copied())
+    }
 }
 
 impl RemoteTrack {
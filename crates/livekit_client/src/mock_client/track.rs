@@ -1,12 +1,18 @@
-use std::sync::Arc;
+use futures::StreamExt as _;
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
 
 use crate::{
-    ParticipantIdentity, TrackSid,
+    ParticipantIdentity, TrackPriority, TrackSid,
     test::{TestServerAudioTrack, TestServerVideoTrack, WeakRoom},
 };
 
-#[derive(Clone, Debug)]
-pub struct LocalVideoTrack {}
+#[derive(Clone, Debug, Default)]
+pub struct LocalVideoTrack {
+    pub(crate) self_view_mirrored: Arc<AtomicBool>,
+}
 
 #[derive(Clone, Debug)]
 pub struct LocalAudioTrack {}
@@ -14,7 +20,7 @@ pub struct LocalAudioTrack {}
 #[derive(Clone, Debug)]
 pub struct RemoteVideoTrack {
     pub(crate) server_track: Arc<TestServerVideoTrack>,
-    pub(crate) _room: WeakRoom,
+    pub(crate) room: WeakRoom,
 }
 
 #[derive(Clone, Debug)]
@@ -23,7 +29,57 @@ pub struct RemoteAudioTrack {
     pub(crate) room: WeakRoom,
 }
 
+impl LocalVideoTrack {
+    pub fn set_self_view_mirrored(&self, mirrored: bool) {
+        self.self_view_mirrored.store(mirrored, Ordering::Relaxed);
+    }
+
+    pub fn is_self_view_mirrored(&self) -> bool {
+        self.self_view_mirrored.load(Ordering::Relaxed)
+    }
+
+    pub fn current_resolution(&self) -> Option<(u32, u32)> {
+        None
+    }
+
+    pub fn encoder_restarts(
+        &self,
+    ) -> futures::channel::mpsc::UnboundedReceiver<crate::EncoderRestart> {
+        futures::channel::mpsc::unbounded().1
+    }
+
+    pub fn total_bytes_sent(&self) -> u64 {
+        0
+    }
+
+    pub fn capture_to_encode_latency(&self) -> Option<std::time::Duration> {
+        None
+    }
+
+    pub fn is_hardware_accelerated(&self) -> Option<bool> {
+        None
+    }
+
+    pub fn add_local_renderer(
+        &self,
+        _callback: impl FnMut(crate::TimedVideoFrame) + Send + 'static,
+        _cx: &gpui::App,
+    ) -> gpui::Task<()> {
+        gpui::Task::ready(())
+    }
+}
+
+impl LocalAudioTrack {
+    pub fn audio_level_updates(&self) -> futures::channel::mpsc::UnboundedReceiver<f32> {
+        futures::channel::mpsc::unbounded().1
+    }
+}
+
 impl RemoteAudioTrack {
+    pub fn audio_level_updates(&self) -> futures::channel::mpsc::UnboundedReceiver<f32> {
+        futures::channel::mpsc::unbounded().1
+    }
+
     pub fn sid(&self) -> TrackSid {
         self.server_track.sid.clone()
     }
@@ -32,6 +88,10 @@ impl RemoteAudioTrack {
         self.server_track.publisher_id.clone()
     }
 
+    pub fn is_silent(&self) -> bool {
+        false
+    }
+
     pub fn enabled(&self) -> bool {
         if let Some(room) = self.room.upgrade() {
             !room
@@ -72,4 +132,87 @@ impl RemoteVideoTrack {
     }
 
     pub(crate) fn set_enabled(&self, _enabled: bool) {}
+
+    pub fn set_priority(&self, priority: TrackPriority) {
+        if let Some(room) = self.room.upgrade() {
+            room.0
+                .lock()
+                .track_priorities
+                .insert(self.server_track.sid.clone(), priority);
+        }
+    }
+
+    pub fn start_recording(
+        &self,
+        _path: &std::path::Path,
+        _cx: &gpui::App,
+    ) -> anyhow::Result<crate::RecordingHandle> {
+        if cfg!(not(target_os = "macos")) {
+            anyhow::bail!("recording is only supported on macOS");
+        }
+        Ok(crate::RecordingHandle {})
+    }
+
+    pub fn layer_stats(&self) -> Vec<crate::LayerStats> {
+        Vec::new()
+    }
+
+    pub fn subscription_stats(&self) -> Option<crate::SubscriptionStats> {
+        None
+    }
+
+    pub fn rtp_parameters(&self) -> Option<crate::RtpParameters> {
+        Some(crate::RtpParameters {
+            mime_type: self.server_track.codec.mime_type().to_string(),
+            clock_rate: 90_000,
+            payload_type: 96,
+        })
+    }
+
+    pub fn total_bytes_received(&self) -> u64 {
+        0
+    }
+
+    pub fn av_sync_offset(&self) -> Option<std::time::Duration> {
+        None
+    }
+
+    pub fn add_renderer(
+        &self,
+        mut callback: impl FnMut(crate::TimedVideoFrame) + Send + 'static,
+        cx: &gpui::App,
+    ) -> gpui::Task<()> {
+        let Some(room) = self.room.upgrade() else {
+            return gpui::Task::ready(());
+        };
+        let mut frames = room
+            .0
+            .lock()
+            .video_renderers
+            .entry(self.sid())
+            .or_default()
+            .subscribe();
+        cx.background_executor().spawn(async move {
+            while let Some(frame) = frames.next().await {
+                callback(frame);
+            }
+        })
+    }
+
+    pub fn is_hardware_accelerated(&self) -> Option<bool> {
+        None
+    }
+
+    pub fn priority(&self) -> TrackPriority {
+        self.room
+            .upgrade()
+            .and_then(|room| {
+                room.0
+                    .lock()
+                    .track_priorities
+                    .get(&self.server_track.sid)
+                    .copied()
+            })
+            .unwrap_or_default()
+    }
 }
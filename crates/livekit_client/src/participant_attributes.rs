@@ -0,0 +1,56 @@
+use std::time::{Duration, SystemTime};
+
+use crate::{ParticipantIdentity, RaisedHand, RecordingConsent};
+
+/// When the participant raised their hand, in milliseconds since the Unix epoch.
+/// The attribute is absent while their hand is down. Storing the time rather than
+/// a flag lets every client order hands the same way without extra signaling.
+pub(crate) const HAND_RAISED_AT: &str = "hand_raised_at";
+
+pub(crate) fn hand_raised_at_value(raised_at: SystemTime) -> String {
+    raised_at
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis()
+        .to_string()
+}
+
+/// Collects the raised hands from each participant's [`HAND_RAISED_AT`] attribute,
+/// earliest first. Values that other clients wrote in an unexpected format are
+/// ignored rather than treated as raised.
+pub(crate) fn raised_hands(
+    participants: impl IntoIterator<Item = (ParticipantIdentity, Option<String>)>,
+) -> Vec<RaisedHand> {
+    let mut hands = participants
+        .into_iter()
+        .filter_map(|(identity, raised_at)| {
+            let millis = raised_at?.parse::<u64>().ok()?;
+            Some(RaisedHand {
+                identity,
+                raised_at: SystemTime::UNIX_EPOCH + Duration::from_millis(millis),
+            })
+        })
+        .collect::<Vec<_>>();
+    hands.sort_by(|a, b| {
+        a.raised_at
+            .cmp(&b.raised_at)
+            .then_with(|| a.identity.cmp(&b.identity))
+    });
+    hands
+}
+
+/// Whether the participant agreed to the room being recorded, either `granted` or
+/// `denied`. The attribute is absent until they've answered.
+pub(crate) const RECORDING_CONSENT: &str = "recording_consent";
+
+pub(crate) fn recording_consent(value: Option<&str>) -> RecordingConsent {
+    match value {
+        Some("granted") => RecordingConsent::Granted,
+        Some("denied") => RecordingConsent::Denied,
+        _ => RecordingConsent::Pending,
+    }
+}
+
+pub(crate) fn recording_consent_value(granted: bool) -> String {
+    if granted { "granted" } else { "denied" }.to_string()
+}
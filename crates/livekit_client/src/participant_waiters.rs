@@ -0,0 +1,62 @@
+use std::time::Duration;
+
+use anyhow::{Result, anyhow};
+use futures::channel::oneshot;
+use gpui::BackgroundExecutor;
+use parking_lot::Mutex;
+
+use crate::{ParticipantIdentity, RemoteParticipant};
+
+/// Pending calls to `Room::wait_for_participant`, resolved as participants join.
+#[derive(Default)]
+pub(crate) struct ParticipantWaiters {
+    waiters: Mutex<Vec<(ParticipantIdentity, oneshot::Sender<RemoteParticipant>)>>,
+}
+
+impl ParticipantWaiters {
+    /// Registers interest in a participant. This must happen before checking whether
+    /// they're already present, so that a join in between the two isn't missed.
+    pub(crate) fn register(
+        &self,
+        identity: ParticipantIdentity,
+    ) -> oneshot::Receiver<RemoteParticipant> {
+        let (tx, rx) = oneshot::channel();
+        let mut waiters = self.waiters.lock();
+        waiters.retain(|(_, tx)| !tx.is_canceled());
+        waiters.push((identity, tx));
+        rx
+    }
+
+    pub(crate) fn participant_connected(&self, participant: &RemoteParticipant) {
+        let identity = participant.identity();
+        let (ready, pending) = std::mem::take(&mut *self.waiters.lock())
+            .into_iter()
+            .partition::<Vec<_>, _>(|(waiting_for, _)| *waiting_for == identity);
+        self.waiters.lock().extend(pending);
+        for (_, tx) in ready {
+            tx.send(participant.clone()).ok();
+        }
+    }
+}
+
+pub(crate) async fn wait_for_participant(
+    identity: ParticipantIdentity,
+    present: Option<RemoteParticipant>,
+    joined: oneshot::Receiver<RemoteParticipant>,
+    timeout: Duration,
+    executor: BackgroundExecutor,
+) -> Result<RemoteParticipant> {
+    if let Some(participant) = present {
+        return Ok(participant);
+    }
+    let timer = executor.timer(timeout);
+    match futures::future::select(joined, timer).await {
+        futures::future::Either::Left((Ok(participant), _)) => Ok(participant),
+        futures::future::Either::Left((Err(_), _)) => {
+            Err(anyhow!("room closed while waiting for {:?}", identity))
+        }
+        futures::future::Either::Right(_) => {
+            Err(anyhow!("{:?} did not join within {:?}", identity, timeout))
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use std::cell::RefCell;
+use std::sync::{Arc, Weak};
+
+use anyhow::Result;
+use futures::{StreamExt as _, channel::mpsc};
+use gpui::{AsyncApp, Task};
+use parking_lot::Mutex;
+
+use crate::{ConnectOptions, DataChannelStats, Room, RoomEvent};
+
+/// Identifies a room within a [`RoomRegistry`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RoomId(u64);
+
+/// Keeps track of every room connected through it, for apps that are in several
+/// calls at once, e.g. to monitor them from a dashboard. Rooms are held weakly and
+/// are forgotten once the app drops them.
+#[derive(Default)]
+pub struct RoomRegistry {
+    rooms: RefCell<Vec<RegisteredRoom>>,
+    next_id: RefCell<u64>,
+    event_txs: Arc<Mutex<Vec<mpsc::UnboundedSender<(RoomId, RoomEvent)>>>>,
+}
+
+struct RegisteredRoom {
+    id: RoomId,
+    room: Weak<Room>,
+    _forward_events: Task<()>,
+}
+
+impl RoomRegistry {
+    /// Connects to a room and registers it. The room's events are only delivered
+    /// through [`Self::events`].
+    pub async fn connect(
+        &self,
+        url: String,
+        token: String,
+        options: ConnectOptions,
+        cx: &mut AsyncApp,
+    ) -> Result<(RoomId, Arc<Room>)> {
+        let (room, mut events) = Room::connect_with_options(url, token, options, cx).await?;
+        let room = Arc::new(room);
+        let id = {
+            let mut next_id = self.next_id.borrow_mut();
+            *next_id += 1;
+            RoomId(*next_id)
+        };
+        let event_txs = self.event_txs.clone();
+        let forward_events = cx.background_executor().spawn(async move {
+            while let Some(event) = events.next().await {
+                event_txs
+                    .lock()
+                    .retain(|tx| tx.unbounded_send((id, event.clone())).is_ok());
+            }
+        });
+        self.rooms.borrow_mut().push(RegisteredRoom {
+            id,
+            room: Arc::downgrade(&room),
+            _forward_events: forward_events,
+        });
+        Ok((id, room))
+    }
+
+    /// The rooms that are still alive, in the order they were connected.
+    pub fn rooms(&self) -> Vec<(RoomId, Arc<Room>)> {
+        let mut rooms = self.rooms.borrow_mut();
+        rooms.retain(|registered| registered.room.strong_count() > 0);
+        rooms
+            .iter()
+            .filter_map(|registered| Some((registered.id, registered.room.upgrade()?)))
+            .collect()
+    }
+
+    pub fn room(&self, id: RoomId) -> Option<Arc<Room>> {
+        self.rooms
+            .borrow()
+            .iter()
+            .find(|registered| registered.id == id)
+            .and_then(|registered| registered.room.upgrade())
+    }
+
+    /// The events of every registered room, tagged with the room they came from.
+    pub fn events(&self) -> mpsc::UnboundedReceiver<(RoomId, RoomEvent)> {
+        let (tx, rx) = mpsc::unbounded();
+        self.event_txs.lock().push(tx);
+        rx
+    }
+
+    /// The number of remote participants across all rooms. Someone present in
+    /// several rooms is counted once per room.
+    pub fn remote_participant_count(&self) -> usize {
+        self.rooms()
+            .iter()
+            .map(|(_, room)| room.remote_participants().len())
+            .sum()
+    }
+
+    /// Data channel totals summed across all rooms.
+    pub fn data_stats(&self) -> DataChannelStats {
+        let mut total = DataChannelStats::default();
+        for (_, room) in self.rooms() {
+            let stats = room.data_stats();
+            total.bytes_sent += stats.bytes_sent;
+            total.bytes_received += stats.bytes_received;
+            total.messages_sent += stats.messages_sent;
+            total.messages_received += stats.messages_received;
+        }
+        total
+    }
+}
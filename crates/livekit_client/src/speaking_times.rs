@@ -0,0 +1,136 @@
+use std::time::{Duration, Instant};
+
+use collections::{BTreeMap, BTreeSet};
+use parking_lot::Mutex;
+
+/// How long each participant has been an active speaker, keyed by participant sid.
+/// A participant gets a new sid every time they join, so these start over on each
+/// connection.
+static SPEAKING_TIMES: Mutex<BTreeMap<String, SpeakingTime>> = Mutex::new(BTreeMap::new());
+
+#[derive(Default)]
+struct SpeakingTime {
+    total: Duration,
+    speaking_since: Option<Instant>,
+}
+
+pub(crate) fn speaking_time(participant_sid: &str) -> Duration {
+    speaking_time_at(participant_sid, Instant::now())
+}
+
+fn speaking_time_at(participant_sid: &str, now: Instant) -> Duration {
+    SPEAKING_TIMES
+        .lock()
+        .get(participant_sid)
+        .map_or(Duration::ZERO, |time| {
+            time.total
+                + time
+                    .speaking_since
+                    .map_or(Duration::ZERO, |since| now.saturating_duration_since(since))
+        })
+}
+
+/// The entries a single room has written into [`SPEAKING_TIMES`], which are removed
+/// once the room is dropped.
+#[derive(Default)]
+pub(crate) struct SpeakingTimes {
+    participant_sids: BTreeSet<String>,
+}
+
+impl SpeakingTimes {
+    /// Records who is speaking as of `now`, given the sids of every active speaker.
+    pub(crate) fn active_speakers_changed(
+        &mut self,
+        speaker_sids: impl IntoIterator<Item = String>,
+        now: Instant,
+    ) {
+        let speaking: BTreeSet<String> = speaker_sids.into_iter().collect();
+        let mut all_times = SPEAKING_TIMES.lock();
+        for sid in &self.participant_sids {
+            if speaking.contains(sid) {
+                continue;
+            }
+            if let Some(time) = all_times.get_mut(sid) {
+                if let Some(since) = time.speaking_since.take() {
+                    time.total += now - since;
+                }
+            }
+        }
+        for sid in speaking {
+            all_times
+                .entry(sid.clone())
+                .or_default()
+                .speaking_since
+                .get_or_insert(now);
+            self.participant_sids.insert(sid);
+        }
+    }
+}
+
+impl Drop for SpeakingTimes {
+    fn drop(&mut self) {
+        let mut all_times = SPEAKING_TIMES.lock();
+        for sid in &self.participant_sids {
+            all_times.remove(sid);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Speaking times are shared by every room in the process, so each test uses
+    // its own sids.
+
+    #[test]
+    fn test_speaking_time_accumulates_across_turns() {
+        let start = Instant::now();
+        let seconds = |n| start + Duration::from_secs(n);
+        let mut speaking_times = SpeakingTimes::default();
+
+        speaking_times.active_speakers_changed(["PA_accumulate_a".to_string()], seconds(0));
+        assert_eq!(
+            speaking_time_at("PA_accumulate_a", seconds(2)),
+            Duration::from_secs(2)
+        );
+
+        // Someone else starting to speak doesn't interrupt the first speaker.
+        speaking_times.active_speakers_changed(
+            ["PA_accumulate_a".to_string(), "PA_accumulate_b".to_string()],
+            seconds(3),
+        );
+        speaking_times.active_speakers_changed(["PA_accumulate_b".to_string()], seconds(5));
+        assert_eq!(
+            speaking_time_at("PA_accumulate_a", seconds(10)),
+            Duration::from_secs(5)
+        );
+
+        speaking_times.active_speakers_changed(["PA_accumulate_a".to_string()], seconds(10));
+        speaking_times.active_speakers_changed(Vec::new(), seconds(11));
+        assert_eq!(
+            speaking_time_at("PA_accumulate_a", seconds(20)),
+            Duration::from_secs(6)
+        );
+        assert_eq!(
+            speaking_time_at("PA_accumulate_b", seconds(20)),
+            Duration::from_secs(7)
+        );
+    }
+
+    #[test]
+    fn test_speaking_times_are_forgotten_with_the_room() {
+        let start = Instant::now();
+        let mut speaking_times = SpeakingTimes::default();
+        speaking_times.active_speakers_changed(["PA_forgotten".to_string()], start);
+        speaking_times.active_speakers_changed(Vec::new(), start + Duration::from_secs(1));
+        assert_eq!(
+            speaking_time_at("PA_forgotten", start),
+            Duration::from_secs(1)
+        );
+
+        drop(speaking_times);
+        assert_eq!(speaking_time_at("PA_forgotten", start), Duration::ZERO);
+        assert_eq!(speaking_time("PA_never_spoke"), Duration::ZERO);
+    }
+}
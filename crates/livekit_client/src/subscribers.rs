@@ -0,0 +1,53 @@
+use futures::channel::mpsc;
+use parking_lot::Mutex;
+
+/// The receivers handed out by one of the room's update streams, e.g.
+/// `Room::data_messages`. Any number can be subscribed, and each is dropped from
+/// the list once its receiver is.
+pub(crate) struct Subscribers<T> {
+    txs: Mutex<Vec<mpsc::UnboundedSender<T>>>,
+}
+
+impl<T> Default for Subscribers<T> {
+    fn default() -> Self {
+        Self {
+            txs: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl<T: Clone> Subscribers<T> {
+    pub(crate) fn subscribe(&self) -> mpsc::UnboundedReceiver<T> {
+        let (tx, rx) = mpsc::unbounded();
+        self.txs.lock().push(tx);
+        rx
+    }
+
+    pub(crate) fn broadcast(&self, value: T) {
+        self.txs
+            .lock()
+            .retain(|tx| tx.unbounded_send(value.clone()).is_ok());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_broadcast_skips_dropped_receivers() {
+        let subscribers = Subscribers::default();
+        let mut first = subscribers.subscribe();
+        let second = subscribers.subscribe();
+        drop(second);
+
+        subscribers.broadcast(1);
+        assert_eq!(first.try_next().unwrap(), Some(1));
+        assert_eq!(subscribers.txs.lock().len(), 1);
+
+        let mut third = subscribers.subscribe();
+        subscribers.broadcast(2);
+        assert_eq!(first.try_next().unwrap(), Some(2));
+        assert_eq!(third.try_next().unwrap(), Some(2));
+    }
+}
@@ -1,6 +1,16 @@
-use crate::{AudioStream, Participant, RemoteTrack, RoomEvent, TrackPublication};
+use crate::{
+    AudioCaptureOptions, AudioStream, BandwidthEstimate, CandidatePair, ConnectOptions,
+    ConnectTiming, ConnectionQuality, ConnectionStateUpdate, DataChannelStats, DataMessage,
+    LiveKitError, Participant, ParticipantPermissions, RaisedHand, RecordingConsent, RemoteTrack,
+    RoomEvent, ScreenShareEvent, TimedVideoFrame, TrackPriority, TrackPublication, TrackSource,
+    TrackStats, Transcription, VideoCodec, VideoPublishOptions, VideoQuality,
+};
 
 use crate::mock_client::{participant::*, publication::*, track::*};
+use crate::participant_attributes::{self, HAND_RAISED_AT, RECORDING_CONSENT};
+use crate::participant_waiters::{self, ParticipantWaiters};
+use crate::subscribers::Subscribers;
+use crate::track_subscription_rules::TrackSubscriptionRules;
 use anyhow::{Context as _, Result, anyhow};
 use async_trait::async_trait;
 use collections::{BTreeMap, HashMap, HashSet, btree_map::Entry as BTreeEntry, hash_map::Entry};
@@ -8,9 +18,12 @@ use gpui::{App, AsyncApp, BackgroundExecutor};
 use livekit_api::{proto, token};
 use parking_lot::Mutex;
 use postage::{mpsc, sink::Sink};
-use std::sync::{
-    Arc, Weak,
-    atomic::{AtomicBool, Ordering::SeqCst},
+use std::{
+    sync::{
+        Arc, Weak,
+        atomic::{AtomicBool, Ordering::SeqCst},
+    },
+    time::Duration,
 };
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
@@ -19,6 +32,15 @@ pub struct ParticipantIdentity(pub String);
 #[derive(Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
 pub struct TrackSid(pub(crate) String);
 
+#[derive(Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+pub struct ParticipantSid(pub(crate) String);
+
+impl std::fmt::Display for ParticipantSid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
 impl std::fmt::Display for TrackSid {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         self.0.fmt(f)
@@ -81,6 +103,12 @@ impl TestServer {
             .clone())
     }
 
+    pub(crate) fn validate_connection(url: &str, token: &str) -> Result<()> {
+        let server = Self::get(url)?;
+        livekit_api::token::validate(token, &server.secret_key)?;
+        Ok(())
+    }
+
     pub fn teardown(&self) -> Result<()> {
         SERVERS
             .lock()
@@ -120,68 +148,43 @@ impl TestServer {
     async fn join_room(&self, token: String, client_room: Room) -> Result<ParticipantIdentity> {
         self.simulate_random_delay().await;
 
-        let claims = livekit_api::token::validate(&token, &self.secret_key)?;
+        let claims = livekit_api::token::validate(&token, &self.secret_key).map_err(|error| {
+            anyhow::Error::new(LiveKitError::TokenInvalid)
+                .context(format!("invalid token: {error}"))
+        })?;
         let identity = ParticipantIdentity(claims.sub.unwrap().to_string());
         let room_name = claims.video.room.unwrap();
         let mut server_rooms = self.rooms.lock();
         let room = (*server_rooms).entry(room_name.to_string()).or_default();
+        if room.client_rooms.contains_key(&identity) {
+            return Err(anyhow!(
+                "{:?} attempted to join room {:?} twice",
+                identity,
+                room_name
+            ));
+        }
 
-        if let Entry::Vacant(e) = room.client_rooms.entry(identity.clone()) {
-            for server_track in &room.video_tracks {
-                let track = RemoteTrack::Video(RemoteVideoTrack {
-                    server_track: server_track.clone(),
-                    _room: client_room.downgrade(),
-                });
-                client_room
-                    .0
-                    .lock()
-                    .updates_tx
-                    .blocking_send(RoomEvent::TrackSubscribed {
-                        track: track.clone(),
-                        publication: RemoteTrackPublication {
-                            sid: server_track.sid.clone(),
-                            room: client_room.downgrade(),
-                            track,
-                        },
-                        participant: RemoteParticipant {
-                            room: client_room.downgrade(),
-                            identity: server_track.publisher_id.clone(),
-                        },
-                    })
-                    .unwrap();
-            }
-            for server_track in &room.audio_tracks {
-                let track = RemoteTrack::Audio(RemoteAudioTrack {
-                    server_track: server_track.clone(),
-                    room: client_room.downgrade(),
-                });
+        let track_sids = room
+            .track_sources()
+            .filter(|(sid, source)| {
+                client_room.is_source_subscribed(*source)
+                    && room.is_subscription_allowed(sid, &identity)
+            })
+            .map(|(sid, _)| sid)
+            .collect::<Vec<_>>();
+        for sid in track_sids {
+            if let Some(event) = room.set_subscribed(&identity, &client_room, &sid, true) {
                 client_room
                     .0
                     .lock()
                     .updates_tx
-                    .blocking_send(RoomEvent::TrackSubscribed {
-                        track: track.clone(),
-                        publication: RemoteTrackPublication {
-                            sid: server_track.sid.clone(),
-                            room: client_room.downgrade(),
-                            track,
-                        },
-                        participant: RemoteParticipant {
-                            room: client_room.downgrade(),
-                            identity: server_track.publisher_id.clone(),
-                        },
-                    })
+                    .blocking_send(event)
                     .unwrap();
             }
-            e.insert(client_room);
-            Ok(identity)
-        } else {
-            Err(anyhow!(
-                "{:?} attempted to join room {:?} twice",
-                identity,
-                room_name
-            ))
         }
+        room.client_rooms.insert(identity.clone(), client_room);
+        room.participant_connected(&identity);
+        Ok(identity)
     }
 
     async fn leave_room(&self, token: String) -> Result<()> {
@@ -201,6 +204,52 @@ impl TestServer {
                 room_name
             )
         })?;
+        room.participant_disconnected(&identity);
+        Ok(())
+    }
+
+    pub(crate) fn participant_permissions(
+        &self,
+        token: &str,
+        identity: &ParticipantIdentity,
+    ) -> ParticipantPermissions {
+        let Ok(claims) = livekit_api::token::validate(token, &self.secret_key) else {
+            return ParticipantPermissions::default();
+        };
+        let room_name = claims.video.room.unwrap();
+        self.rooms
+            .lock()
+            .get(&*room_name)
+            .and_then(|room| room.participant_permissions.get(identity))
+            .map_or(
+                ParticipantPermissions {
+                    can_publish: true,
+                    can_subscribe: true,
+                    can_publish_data: true,
+                    can_publish_sources: Vec::new(),
+                },
+                |permission| ParticipantPermissions {
+                    can_publish: permission.can_publish,
+                    can_subscribe: permission.can_subscribe,
+                    can_publish_data: permission.can_publish_data,
+                    can_publish_sources: Vec::new(),
+                },
+            )
+    }
+
+    pub(crate) async fn update_track_subscription_rules(
+        &self,
+        token: String,
+        update: impl FnOnce(&mut TrackSubscriptionRules),
+    ) -> Result<()> {
+        self.simulate_random_delay().await;
+        let claims = livekit_api::token::validate(&token, &self.secret_key)?;
+        let room_name = claims.video.room.unwrap();
+        let mut server_rooms = self.rooms.lock();
+        let room = server_rooms
+            .get_mut(&*room_name)
+            .ok_or_else(|| anyhow!("room {} does not exist", room_name))?;
+        update(&mut room.track_subscription_rules);
         Ok(())
     }
 
@@ -255,6 +304,7 @@ impl TestServer {
                 room_name
             )
         })?;
+        room.participant_disconnected(&identity);
         Ok(())
     }
 
@@ -275,16 +325,182 @@ impl TestServer {
         Ok(())
     }
 
+    pub async fn send_transcription(
+        &self,
+        room_name: String,
+        transcription: Transcription,
+    ) -> Result<()> {
+        self.simulate_random_delay().await;
+
+        let server_rooms = self.rooms.lock();
+        let room = server_rooms
+            .get(&room_name)
+            .ok_or_else(|| anyhow!("room {} does not exist", room_name))?;
+        for client_room in room.client_rooms.values() {
+            client_room
+                .0
+                .lock()
+                .transcriptions
+                .broadcast(transcription.clone());
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn set_participant_attributes(
+        &self,
+        token: String,
+        attributes: HashMap<String, String>,
+    ) -> Result<()> {
+        self.simulate_random_delay().await;
+
+        let claims = livekit_api::token::validate(&token, &self.secret_key)?;
+        let identity = ParticipantIdentity(claims.sub.unwrap().to_string());
+        let room_name = claims.video.room.unwrap();
+        let mut server_rooms = self.rooms.lock();
+        let room = server_rooms
+            .get_mut(&*room_name)
+            .ok_or_else(|| anyhow!("room {} does not exist", room_name))?;
+        let participant_attributes = room
+            .participant_attributes
+            .entry(identity.clone())
+            .or_default();
+        for (key, value) in &attributes {
+            if value.is_empty() {
+                participant_attributes.remove(key);
+            } else {
+                participant_attributes.insert(key.clone(), value.clone());
+            }
+        }
+        room.attributes_changed(&identity, attributes.keys());
+        Ok(())
+    }
+
+    pub(crate) fn recording_consent(
+        &self,
+        token: &str,
+        identity: &ParticipantIdentity,
+    ) -> RecordingConsent {
+        let Ok(claims) = livekit_api::token::validate(token, &self.secret_key) else {
+            return RecordingConsent::Pending;
+        };
+        let room_name = claims.video.room.unwrap();
+        self.rooms
+            .lock()
+            .get(&*room_name)
+            .map(|room| room.recording_consent(identity))
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn raised_hands(&self, token: &str) -> Vec<RaisedHand> {
+        let Ok(claims) = livekit_api::token::validate(token, &self.secret_key) else {
+            return Vec::new();
+        };
+        let room_name = claims.video.room.unwrap();
+        self.rooms
+            .lock()
+            .get(&*room_name)
+            .map(|room| room.raised_hands())
+            .unwrap_or_default()
+    }
+
+    pub(crate) async fn publish_data(
+        &self,
+        token: String,
+        payload: Vec<u8>,
+        destination: Option<&[ParticipantIdentity]>,
+    ) -> Result<()> {
+        self.simulate_random_delay().await;
+
+        let claims = livekit_api::token::validate(&token, &self.secret_key)?;
+        let identity = ParticipantIdentity(claims.sub.unwrap().to_string());
+        let room_name = claims.video.room.unwrap();
+        let server_rooms = self.rooms.lock();
+        let room = server_rooms
+            .get(&*room_name)
+            .ok_or_else(|| anyhow!("room {} does not exist", room_name))?;
+        let message = DataMessage {
+            sender_identity: Some(identity.clone()),
+            payload,
+        };
+        for (other_identity, client_room) in &room.client_rooms {
+            if *other_identity == identity
+                || destination.is_some_and(|destination| !destination.contains(other_identity))
+            {
+                continue;
+            }
+            client_room
+                .0
+                .lock()
+                .data_messages
+                .broadcast(message.clone());
+        }
+        Ok(())
+    }
+
+    /// Simulates the server reporting who is currently speaking in a room, loudest
+    /// first.
+    pub async fn set_active_speakers(
+        &self,
+        room_name: String,
+        speakers: Vec<ParticipantIdentity>,
+    ) -> Result<()> {
+        self.simulate_random_delay().await;
+
+        let server_rooms = self.rooms.lock();
+        let room = server_rooms
+            .get(&room_name)
+            .ok_or_else(|| anyhow!("room {} does not exist", room_name))?;
+        for client_room in room.client_rooms.values() {
+            client_room
+                .0
+                .lock()
+                .active_speaker_updates
+                .broadcast(speakers.clone());
+        }
+        Ok(())
+    }
+
+    /// Simulates the server rating a participant's connection, which is reported to
+    /// everyone in the room, including the participant themselves.
+    pub async fn set_connection_quality(
+        &self,
+        room_name: String,
+        identity: String,
+        quality: ConnectionQuality,
+    ) -> Result<()> {
+        self.simulate_random_delay().await;
+
+        let server_rooms = self.rooms.lock();
+        let room = server_rooms
+            .get(&room_name)
+            .ok_or_else(|| anyhow!("room {} does not exist", room_name))?;
+        let update = (ParticipantIdentity(identity), quality);
+        for client_room in room.client_rooms.values() {
+            client_room
+                .0
+                .lock()
+                .connection_quality_updates
+                .broadcast(update.clone());
+        }
+        Ok(())
+    }
+
     pub async fn disconnect_client(&self, client_identity: String) {
         let client_identity = ParticipantIdentity(client_identity);
 
         self.simulate_random_delay().await;
 
         let mut server_rooms = self.rooms.lock();
-        for room in server_rooms.values_mut() {
-            if let Some(room) = room.client_rooms.remove(&client_identity) {
+        for server_room in server_rooms.values_mut() {
+            if let Some(room) = server_room.client_rooms.remove(&client_identity) {
+                server_room.participant_disconnected(&client_identity);
                 let mut room = room.0.lock();
                 room.connection_state = ConnectionState::Disconnected;
+                room.connection_state_updates
+                    .broadcast(ConnectionStateUpdate {
+                        state: ConnectionState::Disconnected,
+                        reason: Some("SIGNAL_CLOSED"),
+                    });
                 room.updates_tx
                     .blocking_send(RoomEvent::Disconnected {
                         reason: "SIGNAL_CLOSED",
@@ -294,10 +510,139 @@ impl TestServer {
         }
     }
 
+    /// Simulates the server unpublishing one of a client's tracks, e.g. at a
+    /// moderator's request.
+    pub async fn unpublish_track_by_server(&self, client_identity: String, sid: TrackSid) {
+        let client_identity = ParticipantIdentity(client_identity);
+
+        self.simulate_random_delay().await;
+
+        let server_rooms = self.rooms.lock();
+        for room in server_rooms.values() {
+            if let Some(client_room) = room.client_rooms.get(&client_identity) {
+                client_room
+                    .0
+                    .lock()
+                    .updates_tx
+                    .blocking_send(RoomEvent::LocalTrackUnpublishedByServer { sid: sid.clone() })
+                    .ok();
+            }
+        }
+    }
+
+    pub(crate) async fn set_track_subscribed(
+        &self,
+        token: String,
+        track_sid: TrackSid,
+        subscribed: bool,
+    ) -> Result<()> {
+        self.simulate_random_delay().await;
+
+        let claims = livekit_api::token::validate(&token, &self.secret_key)?;
+        let identity = ParticipantIdentity(claims.sub.unwrap().to_string());
+        let room_name = claims.video.room.unwrap();
+        let (mut updates_tx, event) = {
+            let mut server_rooms = self.rooms.lock();
+            let room = server_rooms
+                .get_mut(&*room_name)
+                .ok_or_else(|| anyhow!("room {} does not exist", room_name))?;
+            let client_room = room
+                .client_rooms
+                .get(&identity)
+                .cloned()
+                .ok_or_else(|| anyhow!("not a participant in room"))?;
+            let Some(event) = room.set_subscribed(&identity, &client_room, &track_sid, subscribed)
+            else {
+                return Ok(());
+            };
+            let updates_tx = client_room.0.lock().updates_tx.clone();
+            (updates_tx, event)
+        };
+        updates_tx.send(event).await.ok();
+        Ok(())
+    }
+
+    /// Subscribes a client to the tracks from the sources it's interested in, and
+    /// unsubscribes it from the rest.
+    pub(crate) async fn update_source_subscriptions(&self, token: String) -> Result<()> {
+        self.simulate_random_delay().await;
+
+        let claims = livekit_api::token::validate(&token, &self.secret_key)?;
+        let identity = ParticipantIdentity(claims.sub.unwrap().to_string());
+        let room_name = claims.video.room.unwrap();
+        let (mut updates_tx, events) = {
+            let mut server_rooms = self.rooms.lock();
+            let room = server_rooms
+                .get_mut(&*room_name)
+                .ok_or_else(|| anyhow!("room {} does not exist", room_name))?;
+            let client_room = room
+                .client_rooms
+                .get(&identity)
+                .cloned()
+                .ok_or_else(|| anyhow!("not a participant in room"))?;
+            let subscriptions = room
+                .track_sources()
+                .map(|(sid, source)| (sid, client_room.is_source_subscribed(source)))
+                .collect::<Vec<_>>();
+            let events = subscriptions
+                .into_iter()
+                .filter_map(|(sid, subscribed)| {
+                    room.set_subscribed(&identity, &client_room, &sid, subscribed)
+                })
+                .collect::<Vec<_>>();
+            let updates_tx = client_room.0.lock().updates_tx.clone();
+            (updates_tx, events)
+        };
+        for event in events {
+            updates_tx.send(event).await.ok();
+        }
+        Ok(())
+    }
+
+    pub(crate) fn is_track_subscribed(&self, token: &str, track_sid: &TrackSid) -> bool {
+        let Ok(claims) = livekit_api::token::validate(token, &self.secret_key) else {
+            return false;
+        };
+        let identity = ParticipantIdentity(claims.sub.unwrap().to_string());
+        let room_name = claims.video.room.unwrap();
+        self.rooms
+            .lock()
+            .get(&*room_name)
+            .is_some_and(|room| room.is_subscribed(&identity, track_sid))
+    }
+
+    /// Simulates a frame arriving on a published video track, which is delivered to
+    /// the renderers of every client subscribed to it.
+    pub async fn send_video_frame(&self, track_sid: TrackSid) -> Result<()> {
+        self.simulate_random_delay().await;
+
+        let server_rooms = self.rooms.lock();
+        let room = server_rooms
+            .values()
+            .find(|room| room.video_tracks.iter().any(|track| track.sid == track_sid))
+            .ok_or_else(|| anyhow!("track {} is not published", track_sid))?;
+        for (identity, client_room) in &room.client_rooms {
+            if !room.is_subscribed(identity, &track_sid) {
+                continue;
+            }
+            if let Some(renderers) = client_room.0.lock().video_renderers.get(&track_sid) {
+                renderers.broadcast(TimedVideoFrame {
+                    frame: crate::mock_client::test_video_frame(),
+                    width: 1,
+                    height: 1,
+                    timestamp: Duration::ZERO,
+                    mirrored: false,
+                });
+            }
+        }
+        Ok(())
+    }
+
     pub(crate) async fn publish_video_track(
         &self,
         token: String,
         _local_track: LocalVideoTrack,
+        options: VideoPublishOptions,
     ) -> Result<TrackSid> {
         self.simulate_random_delay().await;
 
@@ -325,15 +670,18 @@ impl TestServer {
         let server_track = Arc::new(TestServerVideoTrack {
             sid: sid.clone(),
             publisher_id: identity.clone(),
+            codec: options.codec,
         });
 
         room.video_tracks.push(server_track.clone());
 
         for (room_identity, client_room) in &room.client_rooms {
-            if *room_identity != identity {
+            if *room_identity != identity
+                && client_room.is_source_subscribed(TrackSource::Screenshare)
+            {
                 let track = RemoteTrack::Video(RemoteVideoTrack {
                     server_track: server_track.clone(),
-                    _room: client_room.downgrade(),
+                    room: client_room.downgrade(),
                 });
                 let publication = RemoteTrackPublication {
                     sid: sid.clone(),
@@ -344,9 +692,22 @@ impl TestServer {
                     identity: identity.clone(),
                     room: client_room.downgrade(),
                 };
+                let screen_share = ScreenShareEvent::Started {
+                    participant_sid: participant.sid(),
+                    track: RemoteVideoTrack {
+                        server_track: server_track.clone(),
+                        room: client_room.downgrade(),
+                    },
+                };
+                room.subscriptions
+                    .entry(room_identity.clone())
+                    .or_default()
+                    .insert(sid.clone());
+                let mut client_room = client_room.0.lock();
+                client_room
+                    .screen_share_updates
+                    .broadcast(screen_share.clone());
                 client_room
-                    .0
-                    .lock()
                     .updates_tx
                     .blocking_send(RoomEvent::TrackSubscribed {
                         track,
@@ -364,6 +725,7 @@ impl TestServer {
         &self,
         token: String,
         _local_track: &LocalAudioTrack,
+        muted: bool,
     ) -> Result<TrackSid> {
         self.simulate_random_delay().await;
 
@@ -391,13 +753,15 @@ impl TestServer {
         let server_track = Arc::new(TestServerAudioTrack {
             sid: sid.clone(),
             publisher_id: identity.clone(),
-            muted: AtomicBool::new(false),
+            muted: AtomicBool::new(muted),
         });
 
         room.audio_tracks.push(server_track.clone());
 
         for (room_identity, client_room) in &room.client_rooms {
-            if *room_identity != identity {
+            if *room_identity != identity
+                && client_room.is_source_subscribed(TrackSource::Microphone)
+            {
                 let track = RemoteTrack::Audio(RemoteAudioTrack {
                     server_track: server_track.clone(),
                     room: client_room.downgrade(),
@@ -411,6 +775,10 @@ impl TestServer {
                     identity: identity.clone(),
                     room: client_room.downgrade(),
                 };
+                room.subscriptions
+                    .entry(room_identity.clone())
+                    .or_default()
+                    .insert(sid.clone());
                 client_room
                     .0
                     .lock()
@@ -427,7 +795,50 @@ impl TestServer {
         Ok(sid)
     }
 
-    pub(crate) async fn unpublish_track(&self, _token: String, _track: &TrackSid) -> Result<()> {
+    pub(crate) async fn unpublish_track(&self, token: String, track: &TrackSid) -> Result<()> {
+        self.simulate_random_delay().await;
+
+        let claims = livekit_api::token::validate(&token, &self.secret_key)?;
+        let identity = ParticipantIdentity(claims.sub.unwrap().to_string());
+        let room_name = claims.video.room.unwrap();
+
+        let mut server_rooms = self.rooms.lock();
+        let room = server_rooms
+            .get_mut(&*room_name)
+            .ok_or_else(|| anyhow!("room {} does not exist", room_name))?;
+
+        let video_track_count = room.video_tracks.len();
+        let audio_track_count = room.audio_tracks.len();
+        room.video_tracks.retain(|server_track| {
+            server_track.sid != *track || server_track.publisher_id != identity
+        });
+        if room.video_tracks.len() < video_track_count {
+            let screen_share = ScreenShareEvent::Stopped {
+                participant_sid: ParticipantSid(format!("PA_{}", identity.0)),
+                track_sid: track.clone(),
+            };
+            for (room_identity, client_room) in &room.client_rooms {
+                if *room_identity != identity {
+                    client_room
+                        .0
+                        .lock()
+                        .screen_share_updates
+                        .broadcast(screen_share.clone());
+                }
+            }
+        }
+        room.audio_tracks.retain(|server_track| {
+            server_track.sid != *track || server_track.publisher_id != identity
+        });
+        if room.video_tracks.len() == video_track_count
+            && room.audio_tracks.len() == audio_track_count
+        {
+            return Err(anyhow!("track {} is not published", track));
+        }
+        room.track_subscription_rules.remove_track(track);
+        for track_sids in room.subscriptions.values_mut() {
+            track_sids.remove(track);
+        }
         Ok(())
     }
 
@@ -523,7 +934,7 @@ impl TestServer {
             .iter()
             .map(|track| RemoteVideoTrack {
                 server_track: track.clone(),
-                _room: client_room.downgrade(),
+                room: client_room.downgrade(),
             })
             .collect())
     }
@@ -563,12 +974,207 @@ struct TestServerRoom {
     video_tracks: Vec<Arc<TestServerVideoTrack>>,
     audio_tracks: Vec<Arc<TestServerAudioTrack>>,
     participant_permissions: HashMap<ParticipantIdentity, proto::ParticipantPermission>,
+    participant_attributes: HashMap<ParticipantIdentity, HashMap<String, String>>,
+    track_subscription_rules: TrackSubscriptionRules,
+    /// The tracks each participant is subscribed to.
+    subscriptions: HashMap<ParticipantIdentity, HashSet<TrackSid>>,
+}
+
+impl TestServerRoom {
+    /// Restrictions only apply to participants that join afterwards; existing
+    /// subscriptions aren't revoked.
+    fn is_subscription_allowed(
+        &self,
+        track_sid: &TrackSid,
+        identity: &ParticipantIdentity,
+    ) -> bool {
+        self.track_subscription_rules
+            .is_allowed(track_sid, identity)
+    }
+
+    /// Every published track, along with its source. The test server only carries
+    /// screen shares and microphones.
+    fn track_sources(&self) -> impl Iterator<Item = (TrackSid, TrackSource)> + '_ {
+        let video = self
+            .video_tracks
+            .iter()
+            .map(|track| (track.sid.clone(), TrackSource::Screenshare));
+        let audio = self
+            .audio_tracks
+            .iter()
+            .map(|track| (track.sid.clone(), TrackSource::Microphone));
+        video.chain(audio)
+    }
+
+    fn is_subscribed(&self, identity: &ParticipantIdentity, track_sid: &TrackSid) -> bool {
+        self.subscriptions
+            .get(identity)
+            .is_some_and(|track_sids| track_sids.contains(track_sid))
+    }
+
+    /// Subscribes a participant to a track published by someone else, or
+    /// unsubscribes them from it. Returns the event to notify them with, if their
+    /// subscription changed.
+    fn set_subscribed(
+        &mut self,
+        identity: &ParticipantIdentity,
+        client_room: &Room,
+        track_sid: &TrackSid,
+        subscribed: bool,
+    ) -> Option<RoomEvent> {
+        let track = if let Some(server_track) = self
+            .video_tracks
+            .iter()
+            .find(|track| track.sid == *track_sid)
+        {
+            RemoteTrack::Video(RemoteVideoTrack {
+                server_track: server_track.clone(),
+                room: client_room.downgrade(),
+            })
+        } else {
+            let server_track = self
+                .audio_tracks
+                .iter()
+                .find(|track| track.sid == *track_sid)?;
+            RemoteTrack::Audio(RemoteAudioTrack {
+                server_track: server_track.clone(),
+                room: client_room.downgrade(),
+            })
+        };
+        let publisher_id = match &track {
+            RemoteTrack::Audio(track) => track.publisher_id(),
+            RemoteTrack::Video(track) => track.publisher_id(),
+        };
+        if publisher_id == *identity
+            || (subscribed && !self.is_subscription_allowed(track_sid, identity))
+        {
+            return None;
+        }
+
+        let track_sids = self.subscriptions.entry(identity.clone()).or_default();
+        let changed = if subscribed {
+            track_sids.insert(track_sid.clone())
+        } else {
+            track_sids.remove(track_sid)
+        };
+        if !changed {
+            return None;
+        }
+        let publication = RemoteTrackPublication {
+            sid: track_sid.clone(),
+            room: client_room.downgrade(),
+            track: track.clone(),
+        };
+        let participant = RemoteParticipant {
+            identity: publisher_id,
+            room: client_room.downgrade(),
+        };
+        Some(if subscribed {
+            RoomEvent::TrackSubscribed {
+                track,
+                publication,
+                participant,
+            }
+        } else {
+            RoomEvent::TrackUnsubscribed {
+                track,
+                publication,
+                participant,
+            }
+        })
+    }
+
+    /// Announces a participant to everyone else in the room. This happens once per
+    /// join, regardless of how many tracks they go on to publish.
+    fn participant_connected(&self, identity: &ParticipantIdentity) {
+        for (other_identity, other_client_room) in &self.client_rooms {
+            if other_identity != identity {
+                let participant = RemoteParticipant {
+                    identity: identity.clone(),
+                    room: other_client_room.downgrade(),
+                };
+                let waiters = other_client_room.0.lock().participant_waiters.clone();
+                waiters.participant_connected(&participant);
+                other_client_room
+                    .0
+                    .lock()
+                    .updates_tx
+                    .blocking_send(RoomEvent::ParticipantConnected(participant))
+                    .ok();
+            }
+        }
+    }
+
+    fn participant_disconnected(&mut self, identity: &ParticipantIdentity) {
+        self.subscriptions.remove(identity);
+        for other_client_room in self.client_rooms.values() {
+            let participant = RemoteParticipant {
+                identity: identity.clone(),
+                room: other_client_room.downgrade(),
+            };
+            other_client_room
+                .0
+                .lock()
+                .updates_tx
+                .blocking_send(RoomEvent::ParticipantDisconnected(participant))
+                .ok();
+        }
+        let attributes = self
+            .participant_attributes
+            .remove(identity)
+            .unwrap_or_default();
+        self.attributes_changed(identity, attributes.keys());
+    }
+
+    fn attributes_changed<'a>(
+        &self,
+        identity: &ParticipantIdentity,
+        keys: impl IntoIterator<Item = &'a String>,
+    ) {
+        let keys = keys.into_iter().map(String::as_str).collect::<HashSet<_>>();
+        if keys.contains(RECORDING_CONSENT) && self.client_rooms.contains_key(identity) {
+            let update = (identity.clone(), self.recording_consent(identity));
+            for client_room in self.client_rooms.values() {
+                client_room
+                    .0
+                    .lock()
+                    .recording_consent_updates
+                    .broadcast(update.clone());
+            }
+        }
+        if keys.contains(HAND_RAISED_AT) {
+            let hands = self.raised_hands();
+            for client_room in self.client_rooms.values() {
+                client_room.0.lock().hands_updates.broadcast(hands.clone());
+            }
+        }
+    }
+
+    fn recording_consent(&self, identity: &ParticipantIdentity) -> RecordingConsent {
+        participant_attributes::recording_consent(
+            self.participant_attributes
+                .get(identity)
+                .and_then(|attributes| attributes.get(RECORDING_CONSENT))
+                .map(String::as_str),
+        )
+    }
+
+    fn raised_hands(&self) -> Vec<RaisedHand> {
+        participant_attributes::raised_hands(self.client_rooms.keys().map(|identity| {
+            let raised_at = self
+                .participant_attributes
+                .get(identity)
+                .and_then(|attributes| attributes.get(HAND_RAISED_AT).cloned());
+            (identity.clone(), raised_at)
+        }))
+    }
 }
 
 #[derive(Debug)]
 pub(crate) struct TestServerVideoTrack {
     pub(crate) sid: TrackSid,
     pub(crate) publisher_id: ParticipantIdentity,
+    pub(crate) codec: VideoCodec,
     // frames_rx: async_broadcast::Receiver<Frame>,
 }
 
@@ -649,6 +1255,22 @@ pub(crate) struct RoomState {
     pub(crate) local_identity: ParticipantIdentity,
     pub(crate) connection_state: ConnectionState,
     pub(crate) paused_audio_tracks: HashSet<TrackSid>,
+    pub(crate) connection_state_updates: Subscribers<ConnectionStateUpdate>,
+    pub(crate) connection_quality_updates: Subscribers<(ParticipantIdentity, ConnectionQuality)>,
+    pub(crate) track_priorities: HashMap<TrackSid, TrackPriority>,
+    pub(crate) focused_track: Option<(TrackSid, TrackPriority)>,
+    pub(crate) pinned_video_qualities: HashMap<TrackSid, VideoQuality>,
+    pub(crate) visible_video_tracks: Option<HashSet<TrackSid>>,
+    pub(crate) subscribed_sources: Option<HashSet<TrackSource>>,
+    pub(crate) participant_waiters: Arc<ParticipantWaiters>,
+    pub(crate) transcriptions: Subscribers<Transcription>,
+    pub(crate) active_speaker_updates: Subscribers<Vec<ParticipantIdentity>>,
+    pub(crate) screen_share_updates: Subscribers<ScreenShareEvent>,
+    pub(crate) data_messages: Subscribers<DataMessage>,
+    pub(crate) hands_updates: Subscribers<Vec<RaisedHand>>,
+    pub(crate) recording_consent_updates: Subscribers<(ParticipantIdentity, RecordingConsent)>,
+    /// Renderers added through `RemoteVideoTrack::add_renderer`, by track sid.
+    pub(crate) video_renderers: HashMap<TrackSid, Subscribers<TimedVideoFrame>>,
     pub(crate) updates_tx: mpsc::Sender<RoomEvent>,
 }
 
@@ -679,6 +1301,218 @@ impl Room {
         self.0.lock().connection_state
     }
 
+    pub async fn disconnect(&self, _cx: &mut AsyncApp) -> Result<()> {
+        let (server, token) = {
+            let mut state = self.0.lock();
+            if state.connection_state == ConnectionState::Disconnected {
+                return Ok(());
+            }
+            state.connection_state = ConnectionState::Disconnected;
+            (TestServer::get(&state.url)?, state.token.clone())
+        };
+        server.leave_room(token).await
+    }
+
+    pub fn set_audio_output_device(&self, _track: &RemoteAudioTrack, _device_name: Option<String>) {
+    }
+
+    pub fn connect_timing(&self) -> ConnectTiming {
+        ConnectTiming::default()
+    }
+
+    pub fn connection_state_updates(
+        &self,
+    ) -> futures::channel::mpsc::UnboundedReceiver<ConnectionStateUpdate> {
+        self.0.lock().connection_state_updates.subscribe()
+    }
+
+    pub fn connection_quality_updates(
+        &self,
+    ) -> futures::channel::mpsc::UnboundedReceiver<(ParticipantIdentity, ConnectionQuality)> {
+        self.0.lock().connection_quality_updates.subscribe()
+    }
+
+    pub fn paused_video_tracks(&self) -> Vec<TrackSid> {
+        let (token, local_identity, visible_video_tracks) = {
+            let state = self.0.lock();
+            (
+                state.token.clone(),
+                state.local_identity.clone(),
+                state.visible_video_tracks.clone(),
+            )
+        };
+        let Some(visible_video_tracks) = visible_video_tracks else {
+            return Vec::new();
+        };
+        self.test_server()
+            .video_tracks(token)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|track| track.publisher_id() != local_identity)
+            .map(|track| track.sid())
+            .filter(|sid| !visible_video_tracks.contains(sid))
+            .collect()
+    }
+
+    pub fn set_visible_video_tracks(&self, track_sids: Option<HashSet<TrackSid>>, _cx: &App) {
+        self.0.lock().visible_video_tracks = track_sids;
+    }
+
+    pub fn set_focused_track(&self, track_sid: Option<&TrackSid>, _cx: &App) {
+        let mut state = self.0.lock();
+        let state = &mut *state;
+        if state.focused_track.as_ref().map(|(sid, _)| sid) == track_sid {
+            return;
+        }
+        if let Some((sid, priority)) = state.focused_track.take() {
+            state.track_priorities.insert(sid, priority);
+        }
+        if let Some(sid) = track_sid {
+            let priority = state
+                .track_priorities
+                .insert(sid.clone(), TrackPriority::High)
+                .unwrap_or_default();
+            state.focused_track = Some((sid.clone(), priority));
+        }
+    }
+
+    pub fn focused_track(&self) -> Option<TrackSid> {
+        self.0
+            .lock()
+            .focused_track
+            .as_ref()
+            .map(|(sid, _)| sid.clone())
+    }
+
+    pub fn transcriptions_received(
+        &self,
+    ) -> futures::channel::mpsc::UnboundedReceiver<Transcription> {
+        self.0.lock().transcriptions.subscribe()
+    }
+
+    pub fn active_speaker_updates(
+        &self,
+    ) -> futures::channel::mpsc::UnboundedReceiver<Vec<ParticipantIdentity>> {
+        self.0.lock().active_speaker_updates.subscribe()
+    }
+
+    pub fn screen_share_updates(
+        &self,
+    ) -> futures::channel::mpsc::UnboundedReceiver<ScreenShareEvent> {
+        self.0.lock().screen_share_updates.subscribe()
+    }
+
+    pub async fn publish_data(
+        &self,
+        payload: &[u8],
+        reliable: bool,
+        destination: Option<&[ParticipantIdentity]>,
+        _cx: &mut AsyncApp,
+    ) -> Result<()> {
+        crate::check_data_payload_size(payload, reliable)?;
+        self.test_server()
+            .publish_data(self.token(), payload.to_vec(), destination)
+            .await
+    }
+
+    pub fn data_messages(&self) -> futures::channel::mpsc::UnboundedReceiver<DataMessage> {
+        self.0.lock().data_messages.subscribe()
+    }
+
+    pub async fn raise_hand(&self, raised: bool, _cx: &mut AsyncApp) -> Result<()> {
+        let local_identity = self.0.lock().local_identity.clone();
+        let is_raised = self
+            .raised_hands()
+            .iter()
+            .any(|hand| hand.identity == local_identity);
+        if raised == is_raised {
+            return Ok(());
+        }
+        let value = if raised {
+            participant_attributes::hand_raised_at_value(std::time::SystemTime::now())
+        } else {
+            String::new()
+        };
+        self.test_server()
+            .set_participant_attributes(
+                self.token(),
+                [(HAND_RAISED_AT.to_string(), value)].into_iter().collect(),
+            )
+            .await
+    }
+
+    pub fn raised_hands(&self) -> Vec<RaisedHand> {
+        self.test_server().raised_hands(&self.token())
+    }
+
+    pub async fn set_recording_consent(&self, granted: bool, _cx: &mut AsyncApp) -> Result<()> {
+        self.test_server()
+            .set_participant_attributes(
+                self.token(),
+                [(
+                    RECORDING_CONSENT.to_string(),
+                    participant_attributes::recording_consent_value(granted),
+                )]
+                .into_iter()
+                .collect(),
+            )
+            .await
+    }
+
+    pub fn recording_consent_updates(
+        &self,
+    ) -> futures::channel::mpsc::UnboundedReceiver<(ParticipantIdentity, RecordingConsent)> {
+        self.0.lock().recording_consent_updates.subscribe()
+    }
+
+    pub fn hands_updates(&self) -> futures::channel::mpsc::UnboundedReceiver<Vec<RaisedHand>> {
+        self.0.lock().hands_updates.subscribe()
+    }
+
+    pub fn wait_for_participant(
+        &self,
+        identity: &str,
+        timeout: Duration,
+    ) -> impl Future<Output = Result<RemoteParticipant>> + use<> {
+        let identity = ParticipantIdentity(identity.to_string());
+        let waiters = self.0.lock().participant_waiters.clone();
+        let joined = waiters.register(identity.clone());
+        let present = self.remote_participants().remove(&identity);
+        participant_waiters::wait_for_participant(
+            identity,
+            present,
+            joined,
+            timeout,
+            self.test_server().executor.clone(),
+        )
+    }
+
+    pub fn subscribe_sources(&self, sources: &[TrackSource], cx: &App) {
+        self.0.lock().subscribed_sources = Some(sources.iter().copied().collect());
+        self.update_source_subscriptions(cx);
+    }
+
+    pub fn subscribe_all_sources(&self, cx: &App) {
+        self.0.lock().subscribed_sources = None;
+        self.update_source_subscriptions(cx);
+    }
+
+    fn update_source_subscriptions(&self, cx: &App) {
+        let server = self.test_server();
+        let token = self.token();
+        cx.background_executor()
+            .spawn(async move { server.update_source_subscriptions(token).await.ok() })
+            .detach();
+    }
+
+    pub(crate) fn is_source_subscribed(&self, source: TrackSource) -> bool {
+        self.0
+            .lock()
+            .subscribed_sources
+            .as_ref()
+            .map_or(true, |sources| sources.contains(&source))
+    }
+
     pub fn local_participant(&self) -> LocalParticipant {
         let identity = self.0.lock().local_identity.clone();
         LocalParticipant {
@@ -690,9 +1524,19 @@ impl Room {
     pub async fn connect(
         url: String,
         token: String,
+        cx: &mut AsyncApp,
+    ) -> Result<(Self, mpsc::Receiver<RoomEvent>)> {
+        Self::connect_with_options(url, token, ConnectOptions::default(), cx).await
+    }
+
+    pub async fn connect_with_options(
+        url: String,
+        token: String,
+        options: ConnectOptions,
         _cx: &mut AsyncApp,
     ) -> Result<(Self, mpsc::Receiver<RoomEvent>)> {
         let server = TestServer::get(&url)?;
+        let subscribed_sources = (!options.auto_subscribe).then(HashSet::default);
         let (updates_tx, updates_rx) = mpsc::channel(1024);
         let this = Self(Arc::new(Mutex::new(RoomState {
             local_identity: ParticipantIdentity(String::new()),
@@ -700,6 +1544,21 @@ impl Room {
             token: token.to_string(),
             connection_state: ConnectionState::Disconnected,
             paused_audio_tracks: Default::default(),
+            connection_state_updates: Default::default(),
+            connection_quality_updates: Default::default(),
+            track_priorities: Default::default(),
+            focused_track: None,
+            pinned_video_qualities: Default::default(),
+            visible_video_tracks: None,
+            subscribed_sources,
+            participant_waiters: Default::default(),
+            transcriptions: Default::default(),
+            active_speaker_updates: Default::default(),
+            screen_share_updates: Default::default(),
+            data_messages: Default::default(),
+            hands_updates: Default::default(),
+            recording_consent_updates: Default::default(),
+            video_renderers: Default::default(),
             updates_tx,
         })));
 
@@ -716,6 +1575,52 @@ impl Room {
         Ok((this, updates_rx))
     }
 
+    pub async fn set_track_subscription_allowed(
+        &self,
+        track_sid: &TrackSid,
+        participants: Option<&[ParticipantIdentity]>,
+        _cx: &mut AsyncApp,
+    ) -> Result<()> {
+        self.test_server()
+            .update_track_subscription_rules(self.token(), |rules| {
+                rules.set_allowed(track_sid, participants)
+            })
+            .await
+    }
+
+    pub async fn set_track_subscription_denied(
+        &self,
+        track_sid: &TrackSid,
+        participants: Option<&[ParticipantIdentity]>,
+        _cx: &mut AsyncApp,
+    ) -> Result<()> {
+        self.test_server()
+            .update_track_subscription_rules(self.token(), |rules| {
+                rules.set_denied(track_sid, participants)
+            })
+            .await
+    }
+
+    pub fn track_stats(&self, _sid: &TrackSid) -> Option<TrackStats> {
+        None
+    }
+
+    pub fn data_stats(&self) -> DataChannelStats {
+        DataChannelStats::default()
+    }
+
+    pub fn selected_candidate_pair(&self, _include_addresses: bool) -> Option<CandidatePair> {
+        None
+    }
+
+    pub fn available_bandwidth_updates(
+        &self,
+    ) -> futures::channel::mpsc::UnboundedReceiver<BandwidthEstimate> {
+        let (tx, rx) = futures::channel::mpsc::unbounded();
+        tx.unbounded_send(BandwidthEstimate::default()).ok();
+        rx
+    }
+
     pub fn remote_participants(&self) -> HashMap<ParticipantIdentity, RemoteParticipant> {
         self.test_server()
             .remote_participants(self.0.lock().token.clone())
@@ -738,6 +1643,10 @@ impl Room {
         Ok(AudioStream {})
     }
 
+    pub fn measure_audio_loopback_latency(&self) -> impl Future<Output = Result<Duration>> + use<> {
+        async { Ok(Duration::ZERO) }
+    }
+
     pub async fn unpublish_local_track(&self, sid: TrackSid, cx: &mut AsyncApp) -> Result<()> {
         self.local_participant().unpublish_track(sid, cx).await
     }
@@ -746,7 +1655,18 @@ impl Room {
         &self,
         cx: &mut AsyncApp,
     ) -> Result<(LocalTrackPublication, AudioStream)> {
-        self.local_participant().publish_microphone_track(cx).await
+        self.publish_local_microphone_track_with_options(AudioCaptureOptions::default(), cx)
+            .await
+    }
+
+    pub async fn publish_local_microphone_track_with_options(
+        &self,
+        options: AudioCaptureOptions,
+        cx: &mut AsyncApp,
+    ) -> Result<(LocalTrackPublication, AudioStream)> {
+        self.local_participant()
+            .publish_microphone_track(options, cx)
+            .await
     }
 }
 
@@ -769,3 +1689,329 @@ impl WeakRoom {
         self.0.upgrade().map(Room)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{FutureExt as _, StreamExt as _};
+    use gpui::TestAppContext;
+    use livekit_api::Client as _;
+    use std::sync::atomic::AtomicUsize;
+
+    fn test_server(name: &str, cx: &TestAppContext) -> Arc<TestServer> {
+        TestServer::create(name.into(), "key".into(), "secret".into(), cx.executor()).unwrap()
+    }
+
+    async fn connect(
+        server: &TestServer,
+        identity: &str,
+        cx: &mut AsyncApp,
+    ) -> (Room, mpsc::Receiver<RoomEvent>) {
+        connect_with_options(server, identity, ConnectOptions::default(), cx).await
+    }
+
+    async fn connect_with_options(
+        server: &TestServer,
+        identity: &str,
+        options: ConnectOptions,
+        cx: &mut AsyncApp,
+    ) -> (Room, mpsc::Receiver<RoomEvent>) {
+        let token = server
+            .create_api_client()
+            .room_token("test-room", identity)
+            .unwrap();
+        Room::connect_with_options(server.url.clone(), token, options, cx)
+            .await
+            .unwrap()
+    }
+
+    fn subscribed_track_sids(events: &mut mpsc::Receiver<RoomEvent>) -> Vec<TrackSid> {
+        let mut sids = Vec::new();
+        while let Some(Some(event)) = events.next().now_or_never() {
+            if let RoomEvent::TrackSubscribed { publication, .. } = event {
+                sids.push(publication.sid());
+            }
+        }
+        sids
+    }
+
+    fn unsubscribed_track_sids(events: &mut mpsc::Receiver<RoomEvent>) -> Vec<TrackSid> {
+        let mut sids = Vec::new();
+        while let Some(Some(event)) = events.next().now_or_never() {
+            if let RoomEvent::TrackUnsubscribed { publication, .. } = event {
+                sids.push(publication.sid());
+            }
+        }
+        sids
+    }
+
+    #[gpui::test]
+    async fn test_unpublishing_restricted_track(cx: &mut TestAppContext) {
+        let server = test_server("test_unpublishing_restricted_track", cx);
+        let mut async_cx = cx.to_async();
+        let (host, _host_events) = connect(&server, "host", &mut async_cx).await;
+        let local_participant = host.local_participant();
+        let (restricted, _) = local_participant
+            .publish_microphone_track(AudioCaptureOptions::default(), &async_cx)
+            .await
+            .unwrap();
+        let (unrestricted, _) = local_participant
+            .publish_microphone_track(AudioCaptureOptions::default(), &async_cx)
+            .await
+            .unwrap();
+        host.set_track_subscription_allowed(&restricted.sid(), Some(&[]), &mut async_cx)
+            .await
+            .unwrap();
+        local_participant
+            .unpublish_track(restricted.sid(), &async_cx)
+            .await
+            .unwrap();
+
+        let (_guest, mut guest_events) = connect(&server, "guest", &mut async_cx).await;
+        assert_eq!(
+            subscribed_track_sids(&mut guest_events),
+            vec![unrestricted.sid()]
+        );
+
+        server.teardown().unwrap();
+    }
+
+    #[gpui::test]
+    async fn test_transcriptions(cx: &mut TestAppContext) {
+        let server = test_server("test_transcriptions", cx);
+        let mut async_cx = cx.to_async();
+        let (host, _host_events) = connect(&server, "host", &mut async_cx).await;
+        let (guest, _guest_events) = connect(&server, "guest", &mut async_cx).await;
+        let mut host_transcriptions = host.transcriptions_received();
+        let mut guest_transcriptions = guest.transcriptions_received();
+
+        let transcription = Transcription {
+            participant_sid: "PA_host".into(),
+            text: "hello".into(),
+            is_final: true,
+            segment_id: "segment-1".into(),
+        };
+        server
+            .send_transcription("test-room".into(), transcription.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            host_transcriptions.next().await,
+            Some(transcription.clone())
+        );
+        assert_eq!(guest_transcriptions.next().await, Some(transcription));
+
+        server.teardown().unwrap();
+    }
+
+    #[gpui::test]
+    async fn test_active_speaker_updates(cx: &mut TestAppContext) {
+        let server = test_server("test_active_speaker_updates", cx);
+        let mut async_cx = cx.to_async();
+        let (host, _host_events) = connect(&server, "host", &mut async_cx).await;
+        let (_guest, _guest_events) = connect(&server, "guest", &mut async_cx).await;
+        let mut speakers = host.active_speaker_updates();
+
+        let guest_identity = ParticipantIdentity("guest".into());
+        let host_identity = ParticipantIdentity("host".into());
+        server
+            .set_active_speakers(
+                "test-room".into(),
+                vec![guest_identity.clone(), host_identity.clone()],
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            speakers.next().await,
+            Some(vec![guest_identity, host_identity])
+        );
+
+        server
+            .set_active_speakers("test-room".into(), Vec::new())
+            .await
+            .unwrap();
+        assert_eq!(speakers.next().await, Some(Vec::new()));
+
+        server.teardown().unwrap();
+    }
+
+    #[gpui::test]
+    async fn test_track_unpublished_by_server(cx: &mut TestAppContext) {
+        let server = test_server("test_track_unpublished_by_server", cx);
+        let mut async_cx = cx.to_async();
+        let (host, mut host_events) = connect(&server, "host", &mut async_cx).await;
+        let (publication, _) = host
+            .local_participant()
+            .publish_microphone_track(AudioCaptureOptions::default(), &async_cx)
+            .await
+            .unwrap();
+
+        server
+            .unpublish_track_by_server("host".into(), publication.sid())
+            .await;
+        let mut unpublished = Vec::new();
+        while let Some(Some(event)) = host_events.next().now_or_never() {
+            if let RoomEvent::LocalTrackUnpublishedByServer { sid } = event {
+                unpublished.push(sid);
+            }
+        }
+        assert_eq!(unpublished, vec![publication.sid()]);
+
+        server.teardown().unwrap();
+    }
+
+    #[gpui::test]
+    async fn test_subscribing_to_specific_sources(cx: &mut TestAppContext) {
+        let server = test_server("test_subscribing_to_specific_sources", cx);
+        let mut async_cx = cx.to_async();
+        let (host, _host_events) = connect(&server, "host", &mut async_cx).await;
+        let (guest, mut guest_events) = connect(&server, "guest", &mut async_cx).await;
+        let local_participant = host.local_participant();
+        let (first_microphone, _) = local_participant
+            .publish_microphone_track(AudioCaptureOptions::default(), &async_cx)
+            .await
+            .unwrap();
+        assert_eq!(
+            subscribed_track_sids(&mut guest_events),
+            vec![first_microphone.sid()]
+        );
+
+        // The filter applies to tracks that are already published...
+        cx.update(|cx| guest.subscribe_sources(&[TrackSource::Screenshare], cx));
+        cx.executor().run_until_parked();
+        assert_eq!(
+            unsubscribed_track_sids(&mut guest_events),
+            vec![first_microphone.sid()]
+        );
+
+        // ...as well as those published later.
+        let (second_microphone, _) = local_participant
+            .publish_microphone_track(AudioCaptureOptions::default(), &async_cx)
+            .await
+            .unwrap();
+        let (screenshare, _) = local_participant
+            .publish_screenshare_track(&gpui::TestScreenCaptureSource {}, &mut async_cx)
+            .await
+            .unwrap();
+        assert_eq!(
+            subscribed_track_sids(&mut guest_events),
+            vec![screenshare.sid()]
+        );
+
+        cx.update(|cx| guest.subscribe_all_sources(cx));
+        cx.executor().run_until_parked();
+        assert_eq!(
+            subscribed_track_sids(&mut guest_events),
+            vec![first_microphone.sid(), second_microphone.sid()]
+        );
+        let (third_microphone, _) = local_participant
+            .publish_microphone_track(AudioCaptureOptions::default(), &async_cx)
+            .await
+            .unwrap();
+        assert_eq!(
+            subscribed_track_sids(&mut guest_events),
+            vec![third_microphone.sid()]
+        );
+
+        server.teardown().unwrap();
+    }
+
+    #[gpui::test]
+    async fn test_waiting_for_participant(cx: &mut TestAppContext) {
+        let server = test_server("test_waiting_for_participant", cx);
+        let mut async_cx = cx.to_async();
+        let (host, _host_events) = connect(&server, "host", &mut async_cx).await;
+
+        let guest_joined = cx
+            .foreground_executor()
+            .spawn(host.wait_for_participant("guest", Duration::from_secs(10)));
+        let nobody_joined = cx
+            .foreground_executor()
+            .spawn(host.wait_for_participant("nobody", Duration::from_secs(10)));
+        let (_guest, _guest_events) = connect(&server, "guest", &mut async_cx).await;
+        let guest = guest_joined.await.unwrap();
+        assert_eq!(guest.identity(), ParticipantIdentity("guest".into()));
+
+        let guest = host
+            .wait_for_participant("guest", Duration::ZERO)
+            .await
+            .unwrap();
+        assert_eq!(guest.identity(), ParticipantIdentity("guest".into()));
+
+        cx.executor().advance_clock(Duration::from_secs(10));
+        assert!(nobody_joined.await.is_err());
+
+        server.teardown().unwrap();
+    }
+
+    #[gpui::test]
+    async fn test_manual_subscription(cx: &mut TestAppContext) {
+        let server = test_server("test_manual_subscription", cx);
+        let mut async_cx = cx.to_async();
+        let (host, _host_events) = connect(&server, "host", &mut async_cx).await;
+        let (guest, mut guest_events) = connect_with_options(
+            &server,
+            "guest",
+            ConnectOptions {
+                auto_subscribe: false,
+                ..Default::default()
+            },
+            &mut async_cx,
+        )
+        .await;
+
+        let (screenshare, _) = host
+            .local_participant()
+            .publish_screenshare_track(&gpui::TestScreenCaptureSource {}, &mut async_cx)
+            .await
+            .unwrap();
+        assert_eq!(subscribed_track_sids(&mut guest_events), Vec::new());
+
+        let publication = guest.remote_participants()[&ParticipantIdentity("host".into())]
+            .track_publications()
+            .remove(&screenshare.sid())
+            .unwrap();
+        assert!(!publication.is_subscribed());
+        cx.update(|cx| publication.set_subscribed(true, cx));
+        cx.executor().run_until_parked();
+        assert!(publication.is_subscribed());
+        assert_eq!(
+            subscribed_track_sids(&mut guest_events),
+            vec![screenshare.sid()]
+        );
+
+        let Some(RemoteTrack::Video(track)) = publication.track() else {
+            panic!("expected a video track");
+        };
+        let frame_count = Arc::new(AtomicUsize::new(0));
+        let _renderer = cx.update(|cx| {
+            let frame_count = frame_count.clone();
+            track.add_renderer(
+                move |_| {
+                    frame_count.fetch_add(1, SeqCst);
+                },
+                cx,
+            )
+        });
+        server.send_video_frame(screenshare.sid()).await.unwrap();
+        cx.executor().run_until_parked();
+        assert_eq!(frame_count.load(SeqCst), 1);
+
+        // Once unsubscribed, neither frames nor events arrive for the track.
+        cx.update(|cx| publication.set_subscribed(false, cx));
+        cx.executor().run_until_parked();
+        assert!(!publication.is_subscribed());
+        assert!(matches!(
+            guest_events.next().now_or_never(),
+            Some(Some(RoomEvent::TrackUnsubscribed { publication, .. }))
+                if publication.sid() == screenshare.sid()
+        ));
+        server.send_video_frame(screenshare.sid()).await.unwrap();
+        cx.update(|cx| publication.set_subscribed(false, cx));
+        cx.executor().run_until_parked();
+        assert_eq!(frame_count.load(SeqCst), 1);
+        assert!(guest_events.next().now_or_never().is_none());
+
+        server.teardown().unwrap();
+    }
+}
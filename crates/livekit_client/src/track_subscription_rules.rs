@@ -0,0 +1,124 @@
+use collections::{HashMap, HashSet};
+
+use crate::{ParticipantIdentity, TrackSid};
+
+/// Per-track allow and deny lists for who may subscribe to the local participant's
+/// tracks. A participant may subscribe when the track has no allow list or they're
+/// on it, and they aren't on its deny list. Being denied takes precedence over
+/// being allowed.
+#[derive(Default, Debug)]
+pub(crate) struct TrackSubscriptionRules {
+    allowed: HashMap<TrackSid, HashSet<ParticipantIdentity>>,
+    denied: HashMap<TrackSid, HashSet<ParticipantIdentity>>,
+}
+
+impl TrackSubscriptionRules {
+    pub(crate) fn set_allowed(
+        &mut self,
+        track_sid: &TrackSid,
+        participants: Option<&[ParticipantIdentity]>,
+    ) {
+        set_list(&mut self.allowed, track_sid, participants);
+    }
+
+    pub(crate) fn set_denied(
+        &mut self,
+        track_sid: &TrackSid,
+        participants: Option<&[ParticipantIdentity]>,
+    ) {
+        set_list(&mut self.denied, track_sid, participants);
+    }
+
+    pub(crate) fn remove_track(&mut self, track_sid: &TrackSid) {
+        self.allowed.remove(track_sid);
+        self.denied.remove(track_sid);
+    }
+
+    pub(crate) fn is_restricted(&self, track_sid: &TrackSid) -> bool {
+        self.allowed.contains_key(track_sid) || self.denied.contains_key(track_sid)
+    }
+
+    pub(crate) fn is_allowed(&self, track_sid: &TrackSid, identity: &ParticipantIdentity) -> bool {
+        self.allowed
+            .get(track_sid)
+            .is_none_or(|participants| participants.contains(identity))
+            && self
+                .denied
+                .get(track_sid)
+                .is_none_or(|participants| !participants.contains(identity))
+    }
+}
+
+fn set_list(
+    lists: &mut HashMap<TrackSid, HashSet<ParticipantIdentity>>,
+    track_sid: &TrackSid,
+    participants: Option<&[ParticipantIdentity]>,
+) {
+    match participants {
+        Some(participants) => {
+            lists.insert(track_sid.clone(), participants.iter().cloned().collect());
+        }
+        None => {
+            lists.remove(track_sid);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(sid: &str) -> TrackSid {
+        sid.to_string().try_into().unwrap()
+    }
+
+    fn identity(name: &str) -> ParticipantIdentity {
+        ParticipantIdentity(name.to_string())
+    }
+
+    #[test]
+    fn test_unrestricted_tracks_allow_everyone() {
+        let rules = TrackSubscriptionRules::default();
+        assert!(!rules.is_restricted(&track("TR_a")));
+        assert!(rules.is_allowed(&track("TR_a"), &identity("alice")));
+    }
+
+    #[test]
+    fn test_allow_and_deny_lists() {
+        let mut rules = TrackSubscriptionRules::default();
+        rules.set_allowed(&track("TR_a"), Some(&[identity("alice"), identity("bob")]));
+        rules.set_denied(&track("TR_a"), Some(&[identity("bob")]));
+        assert!(rules.is_restricted(&track("TR_a")));
+        assert!(rules.is_allowed(&track("TR_a"), &identity("alice")));
+        // Being denied takes precedence over being allowed.
+        assert!(!rules.is_allowed(&track("TR_a"), &identity("bob")));
+        assert!(!rules.is_allowed(&track("TR_a"), &identity("carol")));
+        // Rules only apply to the track they were set for.
+        assert!(rules.is_allowed(&track("TR_b"), &identity("bob")));
+
+        rules.set_allowed(&track("TR_a"), None);
+        assert!(rules.is_allowed(&track("TR_a"), &identity("carol")));
+        assert!(!rules.is_allowed(&track("TR_a"), &identity("bob")));
+
+        rules.set_denied(&track("TR_a"), None);
+        assert!(!rules.is_restricted(&track("TR_a")));
+    }
+
+    #[test]
+    fn test_empty_allow_list_denies_everyone() {
+        let mut rules = TrackSubscriptionRules::default();
+        rules.set_allowed(&track("TR_a"), Some(&[]));
+        assert!(rules.is_restricted(&track("TR_a")));
+        assert!(!rules.is_allowed(&track("TR_a"), &identity("alice")));
+    }
+
+    #[test]
+    fn test_removing_a_track_clears_its_rules() {
+        let mut rules = TrackSubscriptionRules::default();
+        rules.set_allowed(&track("TR_a"), Some(&[identity("alice")]));
+        rules.set_denied(&track("TR_a"), Some(&[identity("bob")]));
+        rules.remove_track(&track("TR_a"));
+        assert!(!rules.is_restricted(&track("TR_a")));
+        assert!(rules.is_allowed(&track("TR_a"), &identity("bob")));
+    }
+}
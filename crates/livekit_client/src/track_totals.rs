@@ -0,0 +1,69 @@
+use crate::TrackStats;
+
+/// A track's byte counts across peer connections. WebRTC's counters start over
+/// when a reconnect replaces the peer connection, so whatever the track had sent or
+/// received before that is carried over rather than lost.
+#[derive(Default)]
+pub(crate) struct TrackTotals {
+    carried_over: TrackStats,
+    latest: TrackStats,
+}
+
+impl TrackTotals {
+    pub(crate) fn update(&mut self, stats: TrackStats) {
+        if stats.bytes_sent < self.latest.bytes_sent {
+            self.carried_over.bytes_sent += self.latest.bytes_sent;
+        }
+        if stats.bytes_received < self.latest.bytes_received {
+            self.carried_over.bytes_received += self.latest.bytes_received;
+        }
+        self.latest = stats;
+    }
+
+    pub(crate) fn total(&self) -> TrackStats {
+        TrackStats {
+            bytes_sent: self.carried_over.bytes_sent + self.latest.bytes_sent,
+            bytes_received: self.carried_over.bytes_received + self.latest.bytes_received,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(bytes_sent: u64, bytes_received: u64) -> TrackStats {
+        TrackStats {
+            bytes_sent,
+            bytes_received,
+        }
+    }
+
+    #[test]
+    fn test_counts_grow_with_the_latest_poll() {
+        let mut totals = TrackTotals::default();
+        assert_eq!(totals.total(), stats(0, 0));
+        totals.update(stats(100, 10));
+        totals.update(stats(250, 40));
+        assert_eq!(totals.total(), stats(250, 40));
+    }
+
+    #[test]
+    fn test_counts_carry_over_when_counters_reset() {
+        let mut totals = TrackTotals::default();
+        totals.update(stats(100, 50));
+        // A reconnect replaced the peer connection, starting the counters over.
+        totals.update(stats(20, 5));
+        assert_eq!(totals.total(), stats(120, 55));
+        totals.update(stats(30, 60));
+        assert_eq!(totals.total(), stats(130, 110));
+    }
+
+    #[test]
+    fn test_counters_reset_independently() {
+        let mut totals = TrackTotals::default();
+        totals.update(stats(100, 0));
+        totals.update(stats(10, 200));
+        assert_eq!(totals.total(), stats(110, 200));
+    }
+}